@@ -0,0 +1,365 @@
+use crate::scene;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+
+/// How close an orbit camera may get to looking straight up or down, where its view is undefined.
+const MAX_PHI: f32 = FRAC_PI_2 - 0.001;
+const DEFAULT_ZFAR: f32 = 1000.0;
+
+#[derive(Clone)]
+pub enum CameraPosition {
+    SphericalAbout {
+        origin: glam::Vec3,
+        radius: f32,
+        theta: f32,
+        phi: f32,
+    },
+    Absolute {
+        position: glam::Vec3,
+        look_at: glam::Vec3,
+    },
+    /// Placed by a node's world transform, looking down its local -Z axis like a glTF camera.
+    Fixed { transform: glam::Mat4 },
+}
+
+#[derive(Clone)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians.
+        yfov: f32,
+        /// `None` means the aspect ratio of the viewport.
+        aspect_ratio: Option<f32>,
+    },
+    Orthographic {
+        /// Half the width of the view.
+        xmag: f32,
+        /// Half the height of the view.
+        ymag: f32,
+    },
+}
+
+#[derive(Clone)]
+pub struct Camera {
+    pub pos: CameraPosition,
+    pub projection: Projection,
+    /// `None` means an infinite far plane. Orthographic projections fall back to a finite one.
+    pub zfar: Option<f32>,
+    pub znear: f32,
+}
+
+fn pos_from_theta_phi(theta: f32, phi: f32) -> glam::Vec3 {
+    glam::Vec3::new(theta.cos() * phi.cos(), phi.sin(), theta.sin() * phi.cos())
+}
+
+/// Like `glam::Mat4::perspective_rh_gl` with the far plane at infinity.
+fn perspective_infinite_rh_gl(yfov: f32, aspect_ratio: f32, znear: f32) -> glam::Mat4 {
+    let f = 1.0 / (yfov / 2.0).tan();
+    glam::Mat4::from_cols(
+        glam::Vec4::new(f / aspect_ratio, 0.0, 0.0, 0.0),
+        glam::Vec4::new(0.0, f, 0.0, 0.0),
+        glam::Vec4::new(0.0, 0.0, -1.0, -1.0),
+        glam::Vec4::new(0.0, 0.0, -2.0 * znear, 0.0),
+    )
+}
+
+/// The inverse of `pos_from_theta_phi` for a unit vector.
+fn theta_phi_from_pos(pos: glam::Vec3) -> (f32, f32) {
+    (pos.z.atan2(pos.x), pos.y.clamp(-1.0, 1.0).asin())
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            pos: CameraPosition::SphericalAbout {
+                origin: glam::Vec3::ZERO,
+                radius: 3.0,
+                theta: FRAC_PI_2,
+                phi: 0.0,
+            },
+            projection: Projection::Perspective {
+                yfov: FRAC_PI_3,
+                aspect_ratio: None,
+            },
+            zfar: Some(DEFAULT_ZFAR),
+            znear: 0.1,
+        }
+    }
+}
+
+impl Camera {
+    /// Builds a camera that sees exactly what a glTF camera attached to a node with the given
+    /// world transform sees.
+    pub fn from_gltf(camera: &scene::Camera, world_transform: glam::Mat4) -> Self {
+        let pos = CameraPosition::Fixed {
+            transform: world_transform,
+        };
+        match camera.projection {
+            scene::Projection::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => Camera {
+                pos,
+                projection: Projection::Orthographic { xmag, ymag },
+                zfar: Some(zfar),
+                znear,
+            },
+            scene::Projection::Perspective {
+                yfov,
+                aspect_ratio,
+                znear,
+                zfar,
+            } => Camera {
+                pos,
+                projection: Projection::Perspective { yfov, aspect_ratio },
+                zfar,
+                znear,
+            },
+        }
+    }
+
+    /// The distance from the camera to the point it looks at.
+    pub fn target_distance(&self) -> f32 {
+        match self.pos {
+            CameraPosition::SphericalAbout { radius, .. } => radius,
+            CameraPosition::Absolute { position, look_at } => (look_at - position).length(),
+            // There is no target, so use the depth of the scene origin.
+            CameraPosition::Fixed { transform } => {
+                let forward = -transform.z_axis.truncate().normalize();
+                forward.dot(-transform.w_axis.truncate()).max(self.znear)
+            }
+        }
+    }
+
+    /// Switches between perspective and orthographic projection, keeping whatever is at the
+    /// target the same size on screen.
+    pub fn toggle_projection(&mut self, aspect_ratio: f32) {
+        let distance = self.target_distance();
+        self.projection = match self.projection {
+            Projection::Perspective {
+                yfov,
+                aspect_ratio: fixed_aspect_ratio,
+            } => {
+                let ymag = distance * (yfov / 2.0).tan();
+                Projection::Orthographic {
+                    xmag: ymag * fixed_aspect_ratio.unwrap_or(aspect_ratio),
+                    ymag,
+                }
+            }
+            Projection::Orthographic { ymag, .. } => Projection::Perspective {
+                yfov: 2.0 * (ymag / distance).atan(),
+                aspect_ratio: None,
+            },
+        };
+    }
+
+    /// Turns the camera into an orbit camera about the center of `bounds`, far enough away to see
+    /// all of it, keeping the direction it looks in and its projection.
+    pub fn frame(&mut self, bounds: &scene::Aabb, aspect_ratio: f32) {
+        // A single point still needs some room around it.
+        let radius = match bounds.radius() {
+            r if r > 0.0 => r,
+            _ => 1.0,
+        };
+        let (theta, phi) = match self.pos {
+            CameraPosition::SphericalAbout { theta, phi, .. } => (theta, phi),
+            CameraPosition::Absolute { position, look_at } => {
+                theta_phi_from_pos((position - look_at).normalize())
+            }
+            CameraPosition::Fixed { transform } => {
+                theta_phi_from_pos(transform.z_axis.truncate().normalize())
+            }
+        };
+        let distance = match self.projection {
+            Projection::Perspective {
+                yfov,
+                aspect_ratio: fixed_aspect_ratio,
+            } => {
+                let xfov =
+                    2.0 * ((yfov / 2.0).tan() * fixed_aspect_ratio.unwrap_or(aspect_ratio)).atan();
+                radius / (yfov.min(xfov) / 2.0).sin()
+            }
+            Projection::Orthographic {
+                ref mut xmag,
+                ref mut ymag,
+            } => {
+                let scale = radius / xmag.min(*ymag);
+                *xmag *= scale;
+                *ymag *= scale;
+                2.0 * radius
+            }
+        };
+        self.pos = CameraPosition::SphericalAbout {
+            origin: bounds.center(),
+            radius: distance,
+            theta,
+            // A camera looking straight up or down has no up vector to orbit with.
+            phi: phi.clamp(-MAX_PHI, MAX_PHI),
+        };
+        self.znear = radius / 100.0;
+        self.zfar = Some(radius * 100.0);
+    }
+
+    /// Moves an orbit camera around its origin by the given angles in radians.
+    pub fn orbit(&mut self, dtheta: f32, dphi: f32) {
+        if let CameraPosition::SphericalAbout {
+            ref mut theta,
+            ref mut phi,
+            ..
+        } = self.pos
+        {
+            *theta += dtheta;
+            *phi = (*phi + dphi).clamp(-MAX_PHI, MAX_PHI);
+        }
+    }
+
+    /// Slides the camera and the point it looks at across the view, by `offset` times the height
+    /// of the view at that point, so that the scene follows the cursor.
+    pub fn pan(&mut self, offset: glam::Vec2) {
+        let view_height = match self.projection {
+            Projection::Perspective { yfov, .. } => {
+                2.0 * self.target_distance() * (yfov / 2.0).tan()
+            }
+            Projection::Orthographic { ymag, .. } => 2.0 * ymag,
+        };
+        let inverse_view = self.get_view().inverse();
+        let right = inverse_view.x_axis.truncate();
+        let up = inverse_view.y_axis.truncate();
+        let delta = (-offset.x * right + offset.y * up) * view_height;
+        match self.pos {
+            CameraPosition::SphericalAbout { ref mut origin, .. } => *origin += delta,
+            CameraPosition::Absolute {
+                ref mut position,
+                ref mut look_at,
+            } => {
+                *position += delta;
+                *look_at += delta;
+            }
+            CameraPosition::Fixed { .. } => {}
+        }
+    }
+
+    /// Scales an orbit camera's distance to its origin by `factor`, stopping at the near plane.
+    /// Orthographic cameras scale their view instead, as moving them doesn't change what they see.
+    pub fn zoom(&mut self, factor: f32) {
+        match (&mut self.pos, &mut self.projection) {
+            (CameraPosition::SphericalAbout { .. }, Projection::Orthographic { xmag, ymag }) => {
+                *xmag *= factor;
+                *ymag *= factor;
+            }
+            (CameraPosition::SphericalAbout { radius, .. }, Projection::Perspective { .. }) => {
+                *radius = (*radius * factor).max(self.znear);
+            }
+            _ => {}
+        }
+    }
+
+    /// The ray from the camera through a point on screen in normalized device coordinates, as an
+    /// origin and a direction.
+    pub fn ray_through(&self, point: glam::Vec2, aspect_ratio: f32) -> (glam::Vec3, glam::Vec3) {
+        let inverse = (self.get_projection(aspect_ratio) * self.get_view()).inverse();
+        // The far plane may be at infinity, so aim at a point between it and the near plane.
+        let near = inverse.project_point3(point.extend(-1.0));
+        let beyond = inverse.project_point3(point.extend(0.0));
+        (near, (beyond - near).normalize())
+    }
+
+    pub fn get_view(&self) -> glam::Mat4 {
+        match self.pos {
+            CameraPosition::Absolute { position, look_at } => {
+                glam::Mat4::look_at_rh(position, look_at, glam::Vec3::new(0.0, 1.0, 0.0))
+            }
+            CameraPosition::SphericalAbout {
+                origin,
+                radius,
+                theta,
+                phi,
+            } => {
+                let pos = radius * pos_from_theta_phi(theta, phi);
+                let pos = origin + pos;
+                glam::Mat4::look_at_rh(pos, origin, glam::Vec3::new(0.0, 1.0, 0.0))
+            }
+            CameraPosition::Fixed { transform } => transform.inverse(),
+        }
+    }
+
+    pub fn get_projection(&self, aspect_ratio: f32) -> glam::Mat4 {
+        match self.projection {
+            Projection::Perspective {
+                yfov,
+                aspect_ratio: fixed_aspect_ratio,
+            } => {
+                let aspect_ratio = fixed_aspect_ratio.unwrap_or(aspect_ratio);
+                match self.zfar {
+                    Some(zfar) => {
+                        glam::Mat4::perspective_rh_gl(yfov, aspect_ratio, self.znear, zfar)
+                    }
+                    None => perspective_infinite_rh_gl(yfov, aspect_ratio, self.znear),
+                }
+            }
+            Projection::Orthographic { xmag, ymag } => glam::Mat4::orthographic_rh_gl(
+                -xmag,
+                xmag,
+                -ymag,
+                ymag,
+                self.znear,
+                self.zfar.unwrap_or(DEFAULT_ZFAR),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_stops_at_near_plane() {
+        let mut camera = Camera::default();
+        camera.zoom(0.5);
+        assert_eq!(camera.target_distance(), 1.5);
+        for _ in 0..100 {
+            camera.zoom(0.5);
+        }
+        assert_eq!(camera.target_distance(), camera.znear);
+        camera.zoom(2.0);
+        assert_eq!(camera.target_distance(), 2.0 * camera.znear);
+    }
+
+    #[test]
+    fn orthographic_zoom_scales_the_view() {
+        let mut camera = Camera {
+            projection: Projection::Orthographic {
+                xmag: 2.0,
+                ymag: 1.0,
+            },
+            ..Camera::default()
+        };
+        camera.zoom(0.5);
+        assert_eq!(camera.target_distance(), 3.0);
+        match camera.projection {
+            Projection::Orthographic { xmag, ymag } => assert_eq!((xmag, ymag), (1.0, 0.5)),
+            Projection::Perspective { .. } => panic!("zooming changed the projection"),
+        }
+    }
+
+    #[test]
+    fn framing_a_camera_looking_straight_down() {
+        let mut camera = Camera {
+            pos: CameraPosition::Fixed {
+                transform: glam::Mat4::from_rotation_x(-FRAC_PI_2),
+            },
+            ..Camera::default()
+        };
+        let bounds = scene::Aabb {
+            min: glam::Vec3::splat(-1.0),
+            max: glam::Vec3::splat(1.0),
+        };
+        camera.frame(&bounds, 1.0);
+        match camera.pos {
+            CameraPosition::SphericalAbout { phi, .. } => assert!(phi.abs() <= MAX_PHI),
+            _ => panic!("framing didn't make an orbit camera"),
+        }
+        assert!(camera.get_view().is_finite());
+    }
+}
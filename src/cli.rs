@@ -0,0 +1,248 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Lygre: glTF Loader
+
+USAGE:
+    lygre [OPTIONS] <MODEL>
+
+ARGS:
+    <MODEL>    Path to the .gltf or .glb file to view
+
+OPTIONS:
+    -W, --width <PIXELS>         Initial window width [default: 1600]
+    -H, --height <PIXELS>        Initial window height [default: 1080]
+    -c, --camera <N>             Camera to start with, numbered as by the 0-9 keys
+                                 [default: 1 if the scene has a camera, else 0]
+    -s, --scene <INDEX>          Scene to display [default: the file's default scene]
+        --clear-color <R,G,B[,A]>
+                                 Background color, components in [0, 1] [default: 0.5,0.5,0.5,1]
+    -v, --verbose                Print more loader output, repeat for more detail (-vv, -vvv)
+    -q, --quiet                  Only print errors, conflicts with --verbose
+    -h, --help                   Print this message and exit
+
+CONTROLS:
+    Left drag                    Orbit the camera
+    Middle or Shift+left drag    Pan the camera
+    Scroll                       Zoom towards the point the camera orbits
+    P                            Toggle perspective/orthographic projection
+    A                            Frame the whole scene with the free camera
+    F                            Frame the mesh under the cursor with the free camera
+    C, Shift+C                   Switch to the next/previous camera
+    0                            Switch to the free orbit camera
+    1-9                          Switch to the Nth camera node of the scene, in node order
+    Escape                       Quit
+";
+
+pub struct Args {
+    pub model: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// `0` is the free orbit camera, then one per camera node of the scene, in node order.
+    pub camera: Option<usize>,
+    pub scene: Option<usize>,
+    pub clear_color: [f32; 4],
+    pub log_level: log::LevelFilter,
+}
+
+/// What the viewer should do after looking at the command line.
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(mut argv: I) -> Result<Command, String> {
+        let mut model = None;
+        let mut width = 1600;
+        let mut height = 1080;
+        let mut camera = None;
+        let mut scene = None;
+        let mut clear_color = [0.5, 0.5, 0.5, 1.0];
+        let mut verbosity = 0;
+        let mut quiet = false;
+
+        while let Some(arg) = argv.next() {
+            // Allow `--flag=value` as well as `--flag value`.
+            let (flag, inline_value) = match arg.find('=') {
+                Some(idx) if arg.starts_with("--") => {
+                    (arg[..idx].to_string(), Some(arg[idx + 1..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let is_switch = matches!(
+                flag.as_str(),
+                "-h" | "--help" | "-q" | "--quiet" | "-v" | "--verbose" | "-vv" | "-vvv"
+            );
+            if is_switch && inline_value.is_some() {
+                return Err(format!("'{}' does not take a value", flag));
+            }
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| argv.next())
+                    .ok_or_else(|| format!("missing value for '{}'", flag))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-W" | "--width" => width = parse_number(&flag, &value()?)?,
+                "-H" | "--height" => height = parse_number(&flag, &value()?)?,
+                "-c" | "--camera" => camera = Some(parse_number(&flag, &value()?)?),
+                "-s" | "--scene" => scene = Some(parse_number(&flag, &value()?)?),
+                "--clear-color" => clear_color = parse_color(&value()?)?,
+                "-q" | "--quiet" => quiet = true,
+                "-v" | "--verbose" => verbosity += 1,
+                "-vv" => verbosity += 2,
+                "-vvv" => verbosity += 3,
+                other if other.starts_with('-') && other.len() > 1 => {
+                    return Err(format!("unknown option '{}'", other))
+                }
+                _ if model.is_none() => model = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+        }
+
+        if width == 0 || height == 0 {
+            return Err("window size must be non-zero".to_string());
+        }
+
+        if quiet && verbosity > 0 {
+            return Err("'--quiet' and '--verbose' cannot be used together".to_string());
+        }
+
+        let log_level = match verbosity {
+            _ if quiet => log::LevelFilter::Error,
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+
+        Ok(Command::Run(Args {
+            model: model.ok_or_else(|| "missing <MODEL> argument".to_string())?,
+            width,
+            height,
+            camera,
+            scene,
+            clear_color,
+            log_level,
+        }))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "invalid value '{}' for '{}': expected a number",
+            value, flag
+        )
+    })
+}
+
+fn parse_color(value: &str) -> Result<[f32; 4], String> {
+    let components = value
+        .split(',')
+        .map(|c| c.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid color '{}': expected R,G,B or R,G,B,A", value))?;
+
+    if components.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        return Err(format!(
+            "invalid color '{}': components must be in [0, 1]",
+            value
+        ));
+    }
+
+    match components[..] {
+        [r, g, b] => Ok([r, g, b, 1.0]),
+        [r, g, b, a] => Ok([r, g, b, a]),
+        _ => Err(format!(
+            "invalid color '{}': expected 3 or 4 components, got {}",
+            value,
+            components.len()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        match Args::parse(args.iter().map(|arg| arg.to_string()))? {
+            Command::Run(args) => Ok(args),
+            Command::Help => Err("help".to_string()),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&["model.gltf"]).unwrap();
+        assert_eq!(args.model, PathBuf::from("model.gltf"));
+        assert_eq!((args.width, args.height), (1600, 1080));
+        assert!(args.camera.is_none());
+        assert_eq!(args.scene, None);
+        assert_eq!(args.clear_color, [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(args.log_level, log::LevelFilter::Warn);
+    }
+
+    #[test]
+    fn values_inline_and_separate() {
+        let args = parse(&[
+            "--width=800",
+            "-H",
+            "600",
+            "model.glb",
+            "-c",
+            "2",
+            "--scene=1",
+            "--clear-color",
+            "0,0.25,1",
+        ])
+        .unwrap();
+        assert_eq!((args.width, args.height), (800, 600));
+        assert_eq!(args.camera, Some(2));
+        assert_eq!(args.scene, Some(1));
+        assert_eq!(args.clear_color, [0.0, 0.25, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn help() {
+        assert!(matches!(
+            Args::parse(vec!["-h".to_string()].into_iter()),
+            Ok(Command::Help)
+        ));
+    }
+
+    #[test]
+    fn verbosity() {
+        let level = |args: &[&str]| parse(args).unwrap().log_level;
+        assert_eq!(level(&["m.gltf", "-v"]), log::LevelFilter::Info);
+        assert_eq!(level(&["m.gltf", "-v", "-v"]), log::LevelFilter::Debug);
+        assert_eq!(level(&["m.gltf", "-vvv"]), log::LevelFilter::Trace);
+        assert_eq!(level(&["m.gltf", "-q"]), log::LevelFilter::Error);
+        assert!(parse(&["m.gltf", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a.gltf", "b.gltf"]).is_err());
+        assert!(parse(&["m.gltf", "--frobnicate"]).is_err());
+        assert!(parse(&["m.gltf", "--width"]).is_err());
+        assert!(parse(&["m.gltf", "--width", "wide"]).is_err());
+        assert!(parse(&["m.gltf", "--height=0"]).is_err());
+        assert!(parse(&["m.gltf", "--verbose=3"]).is_err());
+        assert!(parse(&["m.gltf", "--help=x"]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_colors() {
+        assert!(parse(&["m.gltf", "--clear-color", "1,1"]).is_err());
+        assert!(parse(&["m.gltf", "--clear-color", "1,1,1,1,1"]).is_err());
+        assert!(parse(&["m.gltf", "--clear-color", "red"]).is_err());
+        assert!(parse(&["m.gltf", "--clear-color", "0,2,0"]).is_err());
+        assert!(parse(&["m.gltf", "--clear-color", "0,-0.5,0"]).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::camera::Camera;
+
+/// What dragging the mouse does to the camera.
+#[derive(Clone, Copy)]
+enum Drag {
+    Orbit,
+    Pan,
+}
+
+/// Mouse state used to drive a [`Camera`] from window events.
+///
+/// Events only queue up movement; [`Input::update`] applies it a little at a time so the camera
+/// eases into place.
+pub struct Input {
+    /// The fraction of the distance to its origin a single scroll step moves an orbit camera.
+    pub scroll_factor: f32,
+    /// How quickly queued movement is applied, per second. Higher is snappier.
+    pub damping: f32,
+    drag: Option<(glfw::MouseButton, Drag)>,
+    mouse_x: f64,
+    mouse_y: f64,
+    /// Orbit angles in radians, not yet applied.
+    orbit: glam::Vec2,
+    /// Cursor movement in pixels while panning, not yet applied.
+    pan: glam::Vec2,
+    /// The log of the zoom factor, not yet applied.
+    zoom: f32,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            scroll_factor: 0.1,
+            damping: 15.0,
+            drag: None,
+            mouse_x: 0.0,
+            mouse_y: 0.0,
+            orbit: glam::Vec2::ZERO,
+            pan: glam::Vec2::ZERO,
+            zoom: 0.0,
+        }
+    }
+}
+
+impl Input {
+    pub fn handle_window_event(&mut self, event: &glfw::WindowEvent) {
+        match *event {
+            glfw::WindowEvent::MouseButton(button, glfw::Action::Press, modifiers)
+                if self.drag.is_none() =>
+            {
+                self.drag = match button {
+                    glfw::MouseButton::Button1 if modifiers.contains(glfw::Modifiers::Shift) => {
+                        Some((button, Drag::Pan))
+                    }
+                    glfw::MouseButton::Button1 => Some((button, Drag::Orbit)),
+                    glfw::MouseButton::Button3 => Some((button, Drag::Pan)),
+                    _ => None,
+                };
+            }
+            glfw::WindowEvent::MouseButton(button, glfw::Action::Release, _) => {
+                if matches!(self.drag, Some((pressed, _)) if pressed == button) {
+                    self.drag = None;
+                }
+            }
+            glfw::WindowEvent::Scroll(_, amount) => {
+                self.zoom += amount as f32 * (1.0 - self.scroll_factor).ln();
+            }
+            glfw::WindowEvent::CursorPos(x, y) => {
+                let (dx, dy) = ((x - self.mouse_x) as f32, (y - self.mouse_y) as f32);
+                match self.drag {
+                    Some((_, Drag::Orbit)) => self.orbit += glam::Vec2::new(dx * 0.004, dy * 0.008),
+                    Some((_, Drag::Pan)) => self.pan += glam::Vec2::new(dx, dy),
+                    None => {}
+                }
+                self.mouse_x = x;
+                self.mouse_y = y;
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies some of the queued movement to `camera`, `dt` seconds after the last update.
+    /// `window_height` is in the same units as cursor positions.
+    pub fn update(&mut self, camera: &mut Camera, dt: f32, window_height: f32) {
+        let portion = 1.0 - (-self.damping * dt).exp();
+        let (orbit, pan, zoom) = (
+            self.orbit * portion,
+            self.pan * portion,
+            self.zoom * portion,
+        );
+        self.orbit -= orbit;
+        self.pan -= pan;
+        self.zoom -= zoom;
+
+        camera.orbit(orbit.x, orbit.y);
+        // A minimized window has no height to pan across.
+        if window_height > 0.0 {
+            camera.pan(pan / window_height);
+        }
+        camera.zoom(zoom.exp());
+    }
+
+    /// Drops any queued movement, such as when switching cameras.
+    pub fn stop(&mut self) {
+        self.orbit = glam::Vec2::ZERO;
+        self.pan = glam::Vec2::ZERO;
+        self.zoom = 0.0;
+    }
+}
@@ -0,0 +1,12 @@
+//! Lygre: a glTF 2.0 loader and OpenGL renderer.
+//!
+//! [`loader::Asset`] reads a `.gltf` or `.glb` file and its buffers and turns it into a
+//! [`scene::Scene`], which doesn't depend on GL. [`renderer::Renderer`] uploads a scene to the GPU
+//! and draws it as seen from a [`camera::Camera`], which [`input::Input`] moves in response to
+//! window events.
+
+pub mod camera;
+pub mod input;
+pub mod loader;
+pub mod renderer;
+pub mod scene;
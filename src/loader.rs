@@ -0,0 +1,1292 @@
+use crate::scene::{self, Location, Scene, MAX_TEX_COORD_SETS};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The glTF extensions the loader understands. Files that require any other extension are
+/// rejected with [`LoadError::UnsupportedExtension`].
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["KHR_lights_punctual", "KHR_mesh_quantization"];
+
+/// Everything that can go wrong while loading a glTF file.
+#[derive(Debug)]
+pub enum LoadError {
+    /// A file referenced by the glTF could not be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The file is not valid glTF.
+    Gltf(gltf::Error),
+    /// A malformed or unsupported `data:` URI.
+    DataUri(String),
+    /// A buffer refers to the binary chunk of a file that has none.
+    MissingBinaryChunk,
+    BufferLength {
+        buffer: usize,
+        expected: usize,
+        actual: usize,
+    },
+    InvalidBufferView {
+        view: usize,
+        reason: String,
+    },
+    InvalidAccessor {
+        accessor: usize,
+        reason: String,
+    },
+    ImageDecode {
+        image: String,
+        reason: String,
+    },
+    /// The requested scene does not exist.
+    MissingScene {
+        index: usize,
+        count: usize,
+    },
+    /// No scene was requested and the file does not contain any.
+    NoScenes,
+    /// The file lists an extension in `extensionsRequired` that is not in
+    /// [`SUPPORTED_EXTENSIONS`].
+    UnsupportedExtension(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            LoadError::Gltf(e) => write!(f, "{}", e),
+            LoadError::DataUri(e) => write!(f, "{}", e),
+            LoadError::MissingBinaryChunk => write!(
+                f,
+                "a buffer refers to a GLB binary chunk, but the file has none"
+            ),
+            LoadError::BufferLength {
+                buffer,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "buffer#{} should be {} bytes long but is {}",
+                buffer, expected, actual
+            ),
+            LoadError::InvalidBufferView { view, reason } => {
+                write!(f, "bufferView#{} is invalid: {}", view, reason)
+            }
+            LoadError::InvalidAccessor { accessor, reason } => {
+                write!(f, "accessor#{} is invalid: {}", accessor, reason)
+            }
+            LoadError::ImageDecode { image, reason } => {
+                write!(f, "could not decode {}: {}", image, reason)
+            }
+            LoadError::MissingScene { index, count } => write!(
+                f,
+                "scene index {} is out of range, the file has {} scene(s)",
+                index, count
+            ),
+            LoadError::NoScenes => write!(f, "the file contains no scenes"),
+            LoadError::UnsupportedExtension(name) => {
+                write!(f, "unsupported extension {} is required", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Gltf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for LoadError {
+    fn from(e: gltf::Error) -> Self {
+        LoadError::Gltf(e)
+    }
+}
+
+/// Loads a `.gltf` or `.glb` file into a [`Scene`], using the file's default scene (or its
+/// first) when `scene_index` is `None`.
+pub fn load_scene<P: AsRef<Path>>(path: P, scene_index: Option<usize>) -> Result<Scene, LoadError> {
+    let asset = Asset::open(path)?;
+    let scene_index = match scene_index {
+        Some(index) => index,
+        None => asset
+            .document
+            .default_scene()
+            .or_else(|| asset.document.scenes().next())
+            .ok_or(LoadError::NoScenes)?
+            .index(),
+    };
+    asset.into_scene(scene_index)
+}
+
+/// A parsed glTF document together with the contents of all of its buffers.
+pub struct Asset {
+    pub path: PathBuf,
+    pub document: gltf::Document,
+    /// The raw bytes of each buffer, indexed by buffer index.
+    pub buffers: Vec<Vec<u8>>,
+}
+
+impl Asset {
+    /// Opens a `.gltf` or `.glb` file and reads every buffer it references.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, LoadError> {
+        let path = path.as_ref().to_path_buf();
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::open(&path)?;
+        if let Some(extension) = document
+            .extensions_required()
+            .find(|extension| !SUPPORTED_EXTENSIONS.contains(extension))
+        {
+            return Err(LoadError::UnsupportedExtension(extension.to_string()));
+        }
+
+        let mut buffers = Vec::new();
+        for buffer in document.buffers() {
+            match buffer.source() {
+                gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                    log::info!("Decoding embedded data URI for buffer#{}", buffer.index());
+                    buffers.push(decode_data_uri(uri).map_err(LoadError::DataUri)?);
+                }
+                gltf::buffer::Source::Uri(filename) => {
+                    log::info!("Attempting to retrieve binary file {}", filename);
+                    let buffer_path = path.with_file_name(filename);
+                    match std::fs::read(&buffer_path) {
+                        Ok(bytes) => buffers.push(bytes),
+                        Err(source) => {
+                            return Err(LoadError::Io {
+                                path: buffer_path,
+                                source,
+                            })
+                        }
+                    }
+                }
+                // The BIN chunk of a .glb file, which the spec says is always buffer 0.
+                gltf::buffer::Source::Bin => {
+                    log::info!("Using the GLB binary chunk for buffer#{}", buffer.index());
+                    buffers.push(blob.take().ok_or(LoadError::MissingBinaryChunk)?);
+                }
+            }
+        }
+
+        check_bounds(&document, &buffers)?;
+        Ok(Asset {
+            path,
+            document,
+            buffers,
+        })
+    }
+
+    /// Decodes an image, wherever it is stored.
+    pub fn load_image(&self, image: &gltf::Image) -> Result<scene::Image, LoadError> {
+        let (load_result, name) = match image.source() {
+            gltf::image::Source::Uri { uri, .. } if uri.starts_with("data:") => (
+                stb_image::image::load_from_memory(
+                    &decode_data_uri(uri).map_err(LoadError::DataUri)?,
+                ),
+                format!("image#{} (data URI)", image.index()),
+            ),
+            gltf::image::Source::Uri { uri, .. } => (
+                stb_image::image::load(self.path.with_file_name(uri)),
+                uri.to_string(),
+            ),
+            gltf::image::Source::View { view, .. } => {
+                let bytes = &self.buffers[view.buffer().index()]
+                    [view.offset()..view.offset() + view.length()];
+                (
+                    stb_image::image::load_from_memory(bytes),
+                    format!("image#{} (bufferView#{})", image.index(), view.index()),
+                )
+            }
+        };
+
+        let (width, height, channels, pixels) = match load_result {
+            stb_image::image::LoadResult::Error(reason) => {
+                return Err(LoadError::ImageDecode {
+                    image: name,
+                    reason,
+                })
+            }
+            stb_image::image::LoadResult::ImageU8(img) => {
+                log::info!(
+                    "Loaded an 8-bit image with {} channels named {}",
+                    img.depth,
+                    name
+                );
+                (
+                    img.width,
+                    img.height,
+                    img.depth,
+                    scene::Pixels::U8(img.data),
+                )
+            }
+            stb_image::image::LoadResult::ImageF32(img) => {
+                log::info!(
+                    "Loaded an 32-bit image with {} channels named {}",
+                    img.depth,
+                    name
+                );
+                (
+                    img.width,
+                    img.height,
+                    img.depth,
+                    scene::Pixels::F32(img.data),
+                )
+            }
+        };
+
+        if !(1..=4).contains(&channels) {
+            return Err(LoadError::ImageDecode {
+                image: name,
+                reason: format!("images with {} channels are not supported", channels),
+            });
+        }
+        Ok(scene::Image {
+            name,
+            width: width as u32,
+            height: height as u32,
+            channels,
+            pixels,
+        })
+    }
+
+    /// Converts the asset into a [`Scene`] containing the nodes of the scene with the given
+    /// index, decoding every image along the way.
+    pub fn into_scene(self, scene_index: usize) -> Result<Scene, LoadError> {
+        let gltf_scene =
+            self.document
+                .scenes()
+                .nth(scene_index)
+                .ok_or_else(|| LoadError::MissingScene {
+                    index: scene_index,
+                    count: self.document.scenes().len(),
+                })?;
+        log::info!("Number of nodes: {}", self.document.nodes().len());
+        let nodes = flatten_nodes(&gltf_scene)
+            .into_iter()
+            .map(|(node, local_transform, world_transform)| scene::Node {
+                index: node.index(),
+                name: node.name().map(str::to_string),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: node.camera().map(|camera| camera.index()),
+                light: node.light().map(|light| light.index()),
+                local_transform,
+                world_transform,
+            })
+            .collect();
+
+        let images = self
+            .document
+            .images()
+            .map(|image| self.load_image(&image))
+            .collect::<Result<_, _>>()?;
+        let Asset {
+            document, buffers, ..
+        } = self;
+
+        let views = document
+            .views()
+            .map(|view| scene::BufferView {
+                buffer: view.buffer().index(),
+                offset: view.offset(),
+                length: view.length(),
+                stride: view.stride(),
+            })
+            .collect();
+
+        let accessors = document
+            .accessors()
+            .map(|accessor| scene::Accessor {
+                view: accessor.view().map(|view| view.index()),
+                offset: accessor.offset(),
+                count: accessor.count(),
+                data_type: accessor.data_type(),
+                dimensions: accessor.dimensions(),
+                normalized: accessor.normalized(),
+                min: accessor.min().as_ref().and_then(json_to_floats),
+                max: accessor.max().as_ref().and_then(json_to_floats),
+            })
+            .collect();
+
+        let textures = document
+            .textures()
+            .map(|texture| {
+                let sampler = texture.sampler();
+                scene::Texture {
+                    image: texture.source().index(),
+                    sampler: scene::Sampler {
+                        mag_filter: sampler.mag_filter(),
+                        min_filter: sampler.min_filter(),
+                        wrap_s: sampler.wrap_s(),
+                        wrap_t: sampler.wrap_t(),
+                    },
+                }
+            })
+            .collect();
+
+        let texture_ref = |info: gltf::texture::Info| scene::TextureRef {
+            texture: info.texture().index(),
+            tex_coord: info.tex_coord(),
+        };
+        let materials = document
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+                let normal = material.normal_texture();
+                let occlusion = material.occlusion_texture();
+                scene::Material {
+                    name: material.name().map(str::to_string),
+                    base_color_factor: pbr.base_color_factor().into(),
+                    base_color_texture: pbr.base_color_texture().map(texture_ref),
+                    metallic_factor: pbr.metallic_factor(),
+                    roughness_factor: pbr.roughness_factor(),
+                    metallic_roughness_texture: pbr.metallic_roughness_texture().map(texture_ref),
+                    normal_texture: normal.as_ref().map(|info| scene::TextureRef {
+                        texture: info.texture().index(),
+                        tex_coord: info.tex_coord(),
+                    }),
+                    normal_scale: normal.as_ref().map_or(1.0, |info| info.scale()),
+                    occlusion_texture: occlusion.as_ref().map(|info| scene::TextureRef {
+                        texture: info.texture().index(),
+                        tex_coord: info.tex_coord(),
+                    }),
+                    occlusion_strength: occlusion.as_ref().map_or(1.0, |info| info.strength()),
+                    emissive_texture: material.emissive_texture().map(texture_ref),
+                    emissive_factor: material.emissive_factor().into(),
+                    alpha_mode: material.alpha_mode(),
+                    alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                    double_sided: material.double_sided(),
+                }
+            })
+            .collect();
+
+        let meshes = document
+            .meshes()
+            .map(|mesh| scene::Mesh {
+                name: mesh.name().map(str::to_string),
+                primitives: mesh
+                    .primitives()
+                    .map(|prim| scene::Primitive {
+                        mode: prim.mode(),
+                        attributes: prim
+                            .attributes()
+                            .map(|(semantic, accessor)| (semantic, accessor.index()))
+                            .collect(),
+                        indices: prim.indices().map(|accessor| accessor.index()),
+                        material: prim.material().index(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let cameras = document
+            .cameras()
+            .map(|camera| scene::Camera {
+                name: camera.name().map(str::to_string),
+                projection: match camera.projection() {
+                    gltf::camera::Projection::Perspective(p) => scene::Projection::Perspective {
+                        yfov: p.yfov(),
+                        aspect_ratio: p.aspect_ratio(),
+                        znear: p.znear(),
+                        zfar: p.zfar(),
+                    },
+                    gltf::camera::Projection::Orthographic(o) => scene::Projection::Orthographic {
+                        xmag: o.xmag(),
+                        ymag: o.ymag(),
+                        znear: o.znear(),
+                        zfar: o.zfar(),
+                    },
+                },
+            })
+            .collect();
+
+        let lights = document
+            .lights()
+            .into_iter()
+            .flatten()
+            .map(|light| scene::Light {
+                name: light.name().map(str::to_string),
+                kind: match light.kind() {
+                    gltf::khr_lights_punctual::Kind::Directional => scene::LightKind::Directional,
+                    gltf::khr_lights_punctual::Kind::Point => scene::LightKind::Point,
+                    gltf::khr_lights_punctual::Kind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    } => scene::LightKind::Spot {
+                        inner_cone_angle,
+                        outer_cone_angle,
+                    },
+                },
+                color: light.color().into(),
+                intensity: light.intensity(),
+                range: light.range(),
+            })
+            .collect();
+
+        let mut scene = Scene {
+            buffers,
+            views,
+            accessors,
+            images,
+            textures,
+            materials,
+            meshes,
+            cameras,
+            lights,
+            nodes,
+            warnings: Vec::new(),
+        };
+        for accessor in document.accessors() {
+            if accessor.sparse().is_some() || accessor.view().is_none() {
+                materialize_accessor(&mut scene, &accessor);
+            }
+        }
+        // Tangents must be generated from the texture coordinates left after any fallbacks.
+        check_support(&document, &mut scene);
+        generate_missing_attributes(&mut scene);
+        Ok(scene)
+    }
+}
+
+/// Records a warning for everything in the document that is loaded but not rendered faithfully,
+/// adjusting the scene where a fallback needs it.
+fn check_support(document: &gltf::Document, scene: &mut Scene) {
+    for extension in document.extensions_used() {
+        if !SUPPORTED_EXTENSIONS.contains(&extension) {
+            scene.warn(
+                Location::Document,
+                format!(
+                    "optional extension {} is not supported, ignoring it",
+                    extension
+                ),
+            );
+        }
+    }
+
+    for (i, image) in scene.images.iter().enumerate() {
+        if let scene::Pixels::F32(_) = image.pixels {
+            scene.warnings.push(scene::Warning {
+                location: Location::Image(i),
+                message: "floating point images are not part of glTF, using it as linear data"
+                    .into(),
+            });
+        }
+    }
+
+    for i in 0..scene.materials.len() {
+        let material = &mut scene.materials[i];
+        let mut messages = Vec::new();
+        if material.alpha_mode != scene::AlphaMode::Opaque {
+            messages.push(format!(
+                "alpha mode {:?} is not supported, drawing it opaque",
+                material.alpha_mode
+            ));
+        }
+        let texture_refs = vec![
+            &mut material.base_color_texture,
+            &mut material.metallic_roughness_texture,
+            &mut material.normal_texture,
+            &mut material.occlusion_texture,
+            &mut material.emissive_texture,
+        ];
+        for texture_ref in texture_refs.into_iter().flatten() {
+            if texture_ref.tex_coord >= MAX_TEX_COORD_SETS {
+                messages.push(format!(
+                    "texture#{} uses TEXCOORD_{}, but only {} sets are supported, using TEXCOORD_0",
+                    texture_ref.texture, texture_ref.tex_coord, MAX_TEX_COORD_SETS
+                ));
+                texture_ref.tex_coord = 0;
+            }
+        }
+        for message in messages {
+            scene.warn(Location::Material(i), message);
+        }
+    }
+
+    for mesh in document.meshes() {
+        if mesh
+            .primitives()
+            .any(|prim| prim.morph_targets().next().is_some())
+        {
+            scene.warn(
+                Location::Mesh(mesh.index()),
+                "morph targets are not supported, drawing the base mesh".into(),
+            );
+        }
+    }
+
+    for node in document.nodes() {
+        if node.skin().is_some() {
+            scene.warn(
+                Location::Node(node.index()),
+                "skinning is not supported, drawing the mesh in its bind pose".into(),
+            );
+        }
+        if node.light().is_some() {
+            scene.warn(
+                Location::Node(node.index()),
+                "KHR_lights_punctual lights are not rendered, using the viewer's lights".into(),
+            );
+        }
+    }
+
+    let animations = document.animations().len();
+    if animations > 0 {
+        scene.warn(
+            Location::Document,
+            format!("{} animation(s) will not play", animations),
+        );
+    }
+}
+
+/// Gives a sparse or view-less accessor a buffer view of its own holding its dense contents, so
+/// that it can be handed to GL like any other.
+fn materialize_accessor(scene: &mut Scene, accessor: &gltf::Accessor) {
+    let index = accessor.index();
+    let size = scene.accessors[index].element_size();
+    let count = scene.accessors[index].count;
+    log::info!("Materializing sparse or view-less accessor#{}", index);
+
+    // Without a view the base values are all zeros.
+    let mut bytes = vec![0u8; count * size];
+    if let Some(view) = scene.accessors[index].view {
+        let view = &scene.views[view];
+        let stride = view.stride.unwrap_or(size);
+        let start = view.offset + scene.accessors[index].offset;
+        let buffer = &scene.buffers[view.buffer];
+        for (i, element) in bytes.chunks_exact_mut(size).enumerate() {
+            element.copy_from_slice(&buffer[start + i * stride..start + i * stride + size]);
+        }
+    }
+
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let index_size = indices.index_type().size();
+        let view = indices.view();
+        let start = view.offset() + indices.offset() as usize;
+        let indices = &scene.buffers[view.buffer().index()][start..];
+
+        let values = sparse.values();
+        let view = values.view();
+        let start = view.offset() + values.offset() as usize;
+        let values = &scene.buffers[view.buffer().index()][start..];
+
+        for i in 0..sparse.count() as usize {
+            let target = match index_size {
+                1 => indices[i] as usize,
+                2 => u16::from_le_bytes([indices[2 * i], indices[2 * i + 1]]) as usize,
+                _ => {
+                    let b = &indices[4 * i..4 * i + 4];
+                    u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize
+                }
+            };
+            if target >= count {
+                scene.warnings.push(scene::Warning {
+                    location: Location::Accessor(index),
+                    message: format!("ignoring out of range sparse index {}", target),
+                });
+                continue;
+            }
+            bytes[target * size..(target + 1) * size]
+                .copy_from_slice(&values[i * size..(i + 1) * size]);
+        }
+    }
+
+    let view = scene.push_view(bytes);
+    let accessor = &mut scene.accessors[index];
+    accessor.view = Some(view);
+    accessor.offset = 0;
+}
+
+/// Fills in the vertex attributes the spec says clients must generate: flat normals for
+/// triangles without `NORMAL`, and MikkTSpace tangents for normal mapped triangles without
+/// `TANGENT`.
+///
+/// Both are per face rather than per vertex, so the primitive's vertices are first unwelded:
+/// every attribute is copied out once per index.
+fn generate_missing_attributes(scene: &mut Scene) {
+    for mesh in 0..scene.meshes.len() {
+        for prim in 0..scene.meshes[mesh].primitives.len() {
+            let primitive = &scene.meshes[mesh].primitives[prim];
+            let is_triangles = matches!(
+                primitive.mode,
+                scene::Mode::Triangles | scene::Mode::TriangleStrip | scene::Mode::TriangleFan
+            );
+            if !is_triangles || primitive.attribute(&scene::Semantic::Positions).is_none() {
+                continue;
+            }
+            let normal_texture = primitive
+                .material
+                .and_then(|material| scene.materials[material].normal_texture);
+            let needs_normals = primitive.attribute(&scene::Semantic::Normals).is_none();
+            let needs_tangents = normal_texture.is_some()
+                && primitive.attribute(&scene::Semantic::Tangents).is_none();
+            if !needs_normals && !needs_tangents {
+                continue;
+            }
+            let location = Location::Primitive {
+                mesh,
+                primitive: prim,
+            };
+            if !unweld(scene, mesh, prim) {
+                scene.warn(
+                    location,
+                    "an index is out of range, so missing normals and tangents were not generated"
+                        .into(),
+                );
+                continue;
+            }
+
+            let primitive = &scene.meshes[mesh].primitives[prim];
+            let positions =
+                scene.read_floats(primitive.attribute(&scene::Semantic::Positions).unwrap());
+            if needs_normals {
+                log::info!(
+                    "Generating flat normals for mesh#{} primitive#{}",
+                    mesh,
+                    prim
+                );
+                let normals = positions
+                    .chunks_exact(9)
+                    .flat_map(|tri| {
+                        let a = glam::Vec3::from_slice(&tri[0..3]);
+                        let b = glam::Vec3::from_slice(&tri[3..6]);
+                        let c = glam::Vec3::from_slice(&tri[6..9]);
+                        vec![(b - a).cross(c - a).normalize_or_zero(); 3]
+                    })
+                    .flat_map(|normal| normal.to_array().to_vec())
+                    .collect::<Vec<_>>();
+                let normals = scene.push_float_accessor(&normals, scene::Dimensions::Vec3);
+                scene.meshes[mesh].primitives[prim]
+                    .attributes
+                    .push((scene::Semantic::Normals, normals));
+            }
+
+            if !needs_tangents {
+                continue;
+            }
+            let primitive = &scene.meshes[mesh].primitives[prim];
+            let tex_coords = normal_texture
+                .and_then(|info| primitive.attribute(&scene::Semantic::TexCoords(info.tex_coord)));
+            let tex_coords = match tex_coords {
+                Some(tex_coords) => tex_coords,
+                None => {
+                    scene.warn(
+                        location,
+                        "no texture coordinates for its normal map, so it has no tangents".into(),
+                    );
+                    continue;
+                }
+            };
+            log::info!("Generating tangents for mesh#{} primitive#{}", mesh, prim);
+            let normals =
+                scene.read_floats(primitive.attribute(&scene::Semantic::Normals).unwrap());
+            let tex_coords = scene.read_floats(tex_coords);
+            let mut geometry = TangentSpace {
+                tangents: vec![0.0; positions.len() / 3 * 4],
+                positions: &positions,
+                normals: &normals,
+                tex_coords: &tex_coords,
+            };
+            if !mikktspace::generate_tangents(&mut geometry) {
+                scene.warn(location, "MikkTSpace failed to generate tangents".into());
+                continue;
+            }
+            let tangents = scene.push_float_accessor(&geometry.tangents, scene::Dimensions::Vec4);
+            scene.meshes[mesh].primitives[prim]
+                .attributes
+                .push((scene::Semantic::Tangents, tangents));
+        }
+    }
+}
+
+/// Replaces a triangle primitive's attributes with copies holding one vertex per index and drops
+/// its indices, so that every triangle has vertices of its own. Strips and fans become plain
+/// triangle lists. Returns false, leaving the primitive untouched, if an index is out of range.
+fn unweld(scene: &mut Scene, mesh: usize, prim: usize) -> bool {
+    let primitive = &scene.meshes[mesh].primitives[prim];
+    let attributes = primitive.attributes.clone();
+    let vertex_count = attributes
+        .iter()
+        .map(|(_, accessor)| scene.accessors[*accessor].count)
+        .min()
+        .unwrap_or(0);
+    let indices = match primitive.indices {
+        Some(indices) => scene.read_indices(indices),
+        None => (0..vertex_count as u32).collect(),
+    };
+    let indices = triangle_list(primitive.mode, &indices);
+    if indices.iter().any(|&i| i as usize >= vertex_count) {
+        return false;
+    }
+
+    let mut unwelded = Vec::with_capacity(attributes.len());
+    for (semantic, accessor) in attributes {
+        let dimensions = scene.accessors[accessor].dimensions;
+        let n = dimensions.multiplicity();
+        let values = scene.read_floats(accessor);
+        let data = indices
+            .iter()
+            .flat_map(|&i| values[i as usize * n..(i as usize + 1) * n].to_vec())
+            .collect::<Vec<_>>();
+        let new_accessor = scene.push_float_accessor(&data, dimensions);
+        // The bounds of normalized accessors are in integer units, not the floats read above.
+        if !scene.accessors[accessor].normalized {
+            scene.accessors[new_accessor].min = scene.accessors[accessor].min.clone();
+            scene.accessors[new_accessor].max = scene.accessors[accessor].max.clone();
+        }
+        unwelded.push((semantic, new_accessor));
+    }
+
+    let primitive = &mut scene.meshes[mesh].primitives[prim];
+    primitive.mode = scene::Mode::Triangles;
+    primitive.attributes = unwelded;
+    primitive.indices = None;
+    true
+}
+
+/// The corners of each triangle of a triangle list, strip or fan, three per triangle.
+fn triangle_list(mode: scene::Mode, indices: &[u32]) -> Vec<u32> {
+    let triangles = indices.len().saturating_sub(2);
+    match mode {
+        // Every other triangle of a strip is flipped to keep the winding consistent.
+        scene::Mode::TriangleStrip => (0..triangles)
+            .flat_map(|i| {
+                let (a, b) = if i % 2 == 0 { (i, i + 1) } else { (i + 1, i) };
+                vec![indices[a], indices[b], indices[i + 2]]
+            })
+            .collect(),
+        scene::Mode::TriangleFan => (0..triangles)
+            .flat_map(|i| vec![indices[i + 1], indices[i + 2], indices[0]])
+            .collect(),
+        _ => indices[..indices.len() - indices.len() % 3].to_vec(),
+    }
+}
+
+/// Unwelded triangle data in the shape [`mikktspace`] expects.
+struct TangentSpace<'a> {
+    positions: &'a [f32],
+    normals: &'a [f32],
+    tex_coords: &'a [f32],
+    /// Four floats per vertex, the last being the handedness of the bitangent.
+    tangents: Vec<f32>,
+}
+
+impl mikktspace::Geometry for TangentSpace<'_> {
+    fn num_faces(&self) -> usize {
+        self.positions.len() / 9
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        let i = (face * 3 + vert) * 3;
+        [
+            self.positions[i],
+            self.positions[i + 1],
+            self.positions[i + 2],
+        ]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        let i = (face * 3 + vert) * 3;
+        [self.normals[i], self.normals[i + 1], self.normals[i + 2]]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let i = (face * 3 + vert) * 2;
+        [self.tex_coords[i], self.tex_coords[i + 1]]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let i = (face * 3 + vert) * 4;
+        self.tangents[i..i + 4].copy_from_slice(&tangent);
+    }
+}
+
+/// Checks that every buffer view, accessor and sparse accessor fits inside the data it points
+/// into, so that nothing later reads past the end of a buffer.
+fn check_bounds(document: &gltf::Document, buffers: &[Vec<u8>]) -> Result<(), LoadError> {
+    for buffer in document.buffers() {
+        let actual = buffers[buffer.index()].len();
+        if actual < buffer.length() {
+            return Err(LoadError::BufferLength {
+                buffer: buffer.index(),
+                expected: buffer.length(),
+                actual,
+            });
+        }
+    }
+
+    for view in document.views() {
+        if view.offset() + view.length() > buffers[view.buffer().index()].len() {
+            return Err(LoadError::InvalidBufferView {
+                view: view.index(),
+                reason: format!(
+                    "bytes {}..{} are past the end of buffer#{}",
+                    view.offset(),
+                    view.offset() + view.length(),
+                    view.buffer().index()
+                ),
+            });
+        }
+    }
+
+    for accessor in document.accessors() {
+        let invalid = |reason: String| LoadError::InvalidAccessor {
+            accessor: accessor.index(),
+            reason,
+        };
+        let size = accessor.size();
+        if let Some(view) = accessor.view() {
+            let stride = view.stride().unwrap_or(size);
+            if stride < size {
+                return Err(invalid(format!(
+                    "its elements are {} bytes but bufferView#{} has a stride of {}",
+                    size,
+                    view.index(),
+                    stride
+                )));
+            }
+            let end = match accessor.count() {
+                0 => accessor.offset(),
+                count => accessor.offset() + (count - 1) * stride + size,
+            };
+            if end > view.length() {
+                return Err(invalid(format!(
+                    "it ends {} bytes into bufferView#{}, which is {} bytes long",
+                    end,
+                    view.index(),
+                    view.length()
+                )));
+            }
+        }
+
+        if let Some(sparse) = accessor.sparse() {
+            let count = sparse.count() as usize;
+            let indices = sparse.indices();
+            let end = indices.offset() as usize + count * indices.index_type().size();
+            if end > indices.view().length() {
+                return Err(invalid(
+                    "its sparse indices overrun their buffer view".into(),
+                ));
+            }
+            let values = sparse.values();
+            if values.offset() as usize + count * size > values.view().length() {
+                return Err(invalid(
+                    "its sparse values overrun their buffer view".into(),
+                ));
+            }
+        }
+    }
+
+    for primitive in document.meshes().flat_map(|mesh| mesh.primitives()) {
+        if let Some(indices) = primitive.indices() {
+            let unsigned = matches!(
+                indices.data_type(),
+                scene::DataType::U8 | scene::DataType::U16 | scene::DataType::U32
+            );
+            if !unsigned || indices.dimensions() != scene::Dimensions::Scalar {
+                return Err(LoadError::InvalidAccessor {
+                    accessor: indices.index(),
+                    reason: format!(
+                        "it is used as indices but holds {:?} {:?}s",
+                        indices.dimensions(),
+                        indices.data_type()
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn json_to_floats(value: &gltf::json::Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|v| v.as_f64().map(|f| f as f32))
+        .collect()
+}
+
+/// The local transform of a node relative to its parent.
+fn node_matrix(node: &gltf::Node) -> glam::Mat4 {
+    match node.transform() {
+        gltf::scene::Transform::Matrix { matrix } => glam::Mat4::from_cols_array_2d(&matrix),
+        gltf::scene::Transform::Decomposed {
+            translation,
+            rotation,
+            scale,
+        } => glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::from_slice(&scale[..]),
+            glam::Quat::from_array(rotation),
+            glam::Vec3::from_slice(&translation[..]),
+        ),
+    }
+}
+
+/// Walks the node hierarchy of a scene breadth-first, returning every node along with its
+/// local and world transforms.
+fn flatten_nodes<'a>(scene: &gltf::Scene<'a>) -> Vec<(gltf::Node<'a>, glam::Mat4, glam::Mat4)> {
+    let mut levels = vec![scene
+        .nodes()
+        .map(|node| {
+            let matrix = node_matrix(&node);
+            (node, matrix, matrix)
+        })
+        .collect::<Vec<_>>()];
+
+    log::trace!(
+        "Root level: {}",
+        levels[0]
+            .iter()
+            .map(|(node, ..)| format!("Node#{} ", node.index()))
+            .collect::<String>()
+    );
+
+    loop {
+        let curr = &levels[levels.len() - 1];
+        let mut to_append = Vec::new();
+        for (node, _, parent_matrix) in curr {
+            log::trace!(
+                "Node #{} has {} children",
+                node.index(),
+                node.children().count()
+            );
+            for child in node.children() {
+                let child_matrix = node_matrix(&child);
+                to_append.push((child, child_matrix, *parent_matrix * child_matrix));
+            }
+        }
+
+        log::trace!(
+            "Next level: {}",
+            to_append
+                .iter()
+                .map(|(node, ..)| format!("Node#{} ", node.index()))
+                .collect::<String>()
+        );
+
+        if to_append.is_empty() {
+            break;
+        }
+
+        levels.push(to_append);
+    }
+
+    levels.into_iter().flatten().collect()
+}
+
+/// Decodes the payload of a `data:[<media type>][;base64],<data>` URI, as used by glTF files
+/// that embed their buffers and images.
+pub fn decode_data_uri(uri: &str) -> Result<Vec<u8>, String> {
+    let payload = uri
+        .strip_prefix("data:")
+        .ok_or_else(|| format!("Not a data URI: {:.32}", uri))?;
+    let comma = payload
+        .find(',')
+        .ok_or_else(|| "Data URI is missing the ',' before its data".to_string())?;
+    let (header, data) = (&payload[..comma], &payload[comma + 1..]);
+    if header.ends_with(";base64") {
+        base64::decode(data).map_err(|e| format!("Invalid base64 in data URI: {}", e))
+    } else {
+        Err(format!(
+            "Only base64 data URIs are supported, got '{}'",
+            header
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(json: &str) -> gltf::Document {
+        gltf::Gltf::from_slice(json.as_bytes()).unwrap().document
+    }
+
+    /// An asset made of a JSON document with a single buffer holding `buffer`.
+    fn asset(json: &str, buffer: Vec<u8>) -> Asset {
+        Asset {
+            path: PathBuf::new(),
+            document: document(json),
+            buffers: vec![buffer],
+        }
+    }
+
+    fn floats_to_bytes(floats: &[f32]) -> Vec<u8> {
+        floats
+            .iter()
+            .flat_map(|f| f.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    /// Writes `json` to a file of its own in the temporary directory.
+    fn write_temp(name: &str, json: &str) -> PathBuf {
+        let name = format!("lygre-{}-{}.gltf", std::process::id(), name);
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, json).unwrap();
+        path
+    }
+
+    #[test]
+    fn decode_data_uri_base64() {
+        assert_eq!(
+            decode_data_uri("data:application/octet-stream;base64,AQID").unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(decode_data_uri("data:;base64,").unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn decode_data_uri_rejects_malformed() {
+        assert!(decode_data_uri("file.bin").is_err());
+        assert!(decode_data_uri("data:application/octet-stream;base64").is_err());
+        assert!(decode_data_uri("data:text/plain,hello").is_err());
+        assert!(decode_data_uri("data:;base64,not base64!").is_err());
+    }
+
+    #[test]
+    fn flat_normals_for_primitives_without_them() {
+        // A triangle in the z = 0 plane, indexed by unsigned shorts.
+        let mut buffer = floats_to_bytes(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        buffer.extend_from_slice(&[0, 0, 1, 0, 2, 0, 0, 0]);
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+            "buffers": [{ "byteLength": 44 }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0, 0, 0], "max": [1, 1, 0] },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+            ]
+        }"#;
+        let scene = asset(json, buffer).into_scene(0).unwrap();
+        let primitive = &scene.meshes[0].primitives[0];
+        let normals = primitive.attribute(&scene::Semantic::Normals).unwrap();
+        assert_eq!(scene.read_floats(normals), [0.0, 0.0, 1.0].repeat(3));
+        // Unwelding copies the positions out in index order, keeping their bounds.
+        let positions = primitive.attribute(&scene::Semantic::Positions).unwrap();
+        assert_eq!(
+            scene.read_floats(positions),
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+        );
+        assert_eq!(scene.accessors[positions].min, Some(vec![0.0, 0.0, 0.0]));
+        assert_eq!(scene.accessors[positions].max, Some(vec![1.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn unwelding_drops_bounds_of_normalized_accessors() {
+        // A triangle with normalized unsigned short texture coordinates, and no normals.
+        let mut buffer = floats_to_bytes(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        buffer.extend_from_slice(&[0, 0, 0, 0, 255, 255, 0, 0, 0, 0, 255, 255]);
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "TEXCOORD_0": 1 } }] }],
+            "buffers": [{ "byteLength": 48 }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 12 }
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0, 0, 0], "max": [1, 1, 0] },
+                { "bufferView": 1, "componentType": 5123, "normalized": true, "count": 3,
+                  "type": "VEC2", "min": [0, 0], "max": [65535, 65535] }
+            ]
+        }"#;
+        let scene = asset(json, buffer).into_scene(0).unwrap();
+        let primitive = &scene.meshes[0].primitives[0];
+        let tex_coords = primitive.attribute(&scene::Semantic::TexCoords(0)).unwrap();
+        assert_eq!(
+            scene.read_floats(tex_coords),
+            vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(scene.accessors[tex_coords].min, None);
+        assert_eq!(scene.accessors[tex_coords].max, None);
+    }
+
+    #[test]
+    fn triangle_list_keeps_strip_winding() {
+        assert_eq!(
+            triangle_list(scene::Mode::TriangleStrip, &[0, 1, 2, 3, 4]),
+            vec![0, 1, 2, 2, 1, 3, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn triangle_list_fans_around_first_vertex() {
+        assert_eq!(
+            triangle_list(scene::Mode::TriangleFan, &[0, 1, 2, 3]),
+            vec![1, 2, 0, 2, 3, 0]
+        );
+    }
+
+    #[test]
+    fn triangle_list_drops_incomplete_triangles() {
+        assert_eq!(
+            triangle_list(scene::Mode::Triangles, &[0, 1, 2, 3]),
+            vec![0, 1, 2]
+        );
+        assert!(triangle_list(scene::Mode::TriangleStrip, &[0, 1]).is_empty());
+    }
+
+    #[test]
+    fn materialize_sparse_and_view_less_accessors() {
+        // Base values 1, 2, 3, 4, then sparse indices 1 and 3 as bytes, then values 10 and 30.
+        let mut buffer = floats_to_bytes(&[1.0, 2.0, 3.0, 4.0]);
+        buffer.extend_from_slice(&[1, 3, 0, 0]);
+        buffer.extend(floats_to_bytes(&[10.0, 30.0]));
+        let sparse = r#"{
+            "count": 2,
+            "indices": { "bufferView": 1, "componentType": 5121 },
+            "values": { "bufferView": 2 }
+        }"#;
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scenes": [{{ "nodes": [] }}],
+                "buffers": [{{ "byteLength": 28 }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 16 }},
+                    {{ "buffer": 0, "byteOffset": 16, "byteLength": 2 }},
+                    {{ "buffer": 0, "byteOffset": 20, "byteLength": 8 }}
+                ],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "SCALAR",
+                       "sparse": {sparse} }},
+                    {{ "componentType": 5126, "count": 4, "type": "SCALAR", "sparse": {sparse} }}
+                ]
+            }}"#,
+            sparse = sparse
+        );
+        let scene = asset(&json, buffer).into_scene(0).unwrap();
+        assert_eq!(scene.read_floats(0), vec![1.0, 10.0, 3.0, 30.0]);
+        assert_eq!(scene.read_floats(1), vec![0.0, 10.0, 0.0, 30.0]);
+        assert!(scene
+            .accessors
+            .iter()
+            .all(|accessor| accessor.view.is_some()));
+        assert!(scene.warnings.is_empty());
+    }
+
+    /// A document with one 16 byte buffer, one view and one float accessor over it.
+    fn bounds_document(view: &str, accessor: &str) -> gltf::Document {
+        document(&format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": 16 }}],
+                "bufferViews": [{{ "buffer": 0, {} }}],
+                "accessors": [{{ "bufferView": 0, "componentType": 5126, {} }}]
+            }}"#,
+            view, accessor
+        ))
+    }
+
+    #[test]
+    fn check_bounds_accepts_data_in_range() {
+        let document = bounds_document(
+            r#""byteOffset": 4, "byteLength": 12"#,
+            r#""byteOffset": 4, "count": 2, "type": "SCALAR""#,
+        );
+        assert!(check_bounds(&document, &[vec![0; 16]]).is_ok());
+    }
+
+    #[test]
+    fn check_bounds_rejects_short_buffers() {
+        let document = bounds_document(r#""byteLength": 16"#, r#""count": 4, "type": "SCALAR""#);
+        assert!(matches!(
+            check_bounds(&document, &[vec![0; 8]]),
+            Err(LoadError::BufferLength {
+                buffer: 0,
+                expected: 16,
+                actual: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn check_bounds_rejects_view_overruns() {
+        let document = bounds_document(
+            r#""byteOffset": 8, "byteLength": 16"#,
+            r#""count": 1, "type": "SCALAR""#,
+        );
+        assert!(matches!(
+            check_bounds(&document, &[vec![0; 16]]),
+            Err(LoadError::InvalidBufferView { view: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn check_bounds_rejects_accessor_overruns() {
+        let document = bounds_document(
+            r#""byteLength": 16"#,
+            r#""byteOffset": 4, "count": 2, "type": "VEC2""#,
+        );
+        assert!(matches!(
+            check_bounds(&document, &[vec![0; 16]]),
+            Err(LoadError::InvalidAccessor { accessor: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn load_scene_without_scenes() {
+        let path = write_temp("no-scenes", r#"{ "asset": { "version": "2.0" } }"#);
+        let result = load_scene(&path, None);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(LoadError::NoScenes)));
+    }
+
+    #[test]
+    fn load_scene_out_of_range() {
+        let path = write_temp(
+            "one-scene",
+            r#"{ "asset": { "version": "2.0" }, "scenes": [{ "nodes": [] }] }"#,
+        );
+        let missing = load_scene(&path, Some(1));
+        let first = load_scene(&path, None);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            missing,
+            Err(LoadError::MissingScene { index: 1, count: 1 })
+        ));
+        assert!(first.is_ok());
+    }
+
+    #[test]
+    fn open_rejects_unsupported_required_extensions() {
+        let required = |name: &str, extension: &str| {
+            let path = write_temp(
+                name,
+                &format!(
+                    r#"{{
+                        "asset": {{ "version": "2.0" }},
+                        "extensionsUsed": ["{0}"],
+                        "extensionsRequired": ["{0}"]
+                    }}"#,
+                    extension
+                ),
+            );
+            let result = Asset::open(&path);
+            std::fs::remove_file(&path).unwrap();
+            result
+        };
+        assert!(matches!(
+            required("unsupported-extension", "EXT_made_up"),
+            Err(LoadError::UnsupportedExtension(ref extension)) if extension == "EXT_made_up"
+        ));
+        assert!(required("supported-extension", "KHR_mesh_quantization").is_ok());
+    }
+}
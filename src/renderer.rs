@@ -0,0 +1,791 @@
+use crate::camera::Camera;
+use crate::scene::{self, Scene, MAX_TEX_COORD_SETS};
+use std::collections::HashMap;
+
+// Vertex attribute locations, matching the layout qualifiers in `VERTEX_SOURCE`.
+const POSITION_LOCATION: u32 = 0;
+const NORMAL_LOCATION: u32 = 1;
+const TANGENT_LOCATION: u32 = 3;
+const COLOR_LOCATION: u32 = 5;
+/// The location of each `TEXCOORD_n`.
+const TEX_COORD_LOCATIONS: [u32; MAX_TEX_COORD_SETS as usize] = [2, 4];
+
+/// A texture on the GPU, along with the UV set a material samples it with.
+#[derive(Clone, Copy, Debug)]
+pub struct Texture {
+    pub id: u32,
+    pub tex_coord: u32,
+}
+
+/// A glTF material whose textures have been uploaded to the GPU.
+pub struct Material {
+    pub base_color_factor: glam::Vec4,
+    pub base_color_texture: Option<Texture>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<Texture>,
+    pub normal_texture: Option<Texture>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<Texture>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<Texture>,
+    pub emissive_factor: glam::Vec3,
+    pub alpha_mode: scene::AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+/// A mesh primitive ready to be drawn, placed in the world by the node that instanced it.
+pub struct Primitive {
+    /// The GL primitive type, e.g. `gl::TRIANGLES`.
+    pub mode: u32,
+    pub vao: u32,
+    /// `None` for primitives without indices, which are drawn with `glDrawArrays`.
+    pub ebo: Option<u32>,
+    /// The GL type of each index, e.g. `gl::UNSIGNED_SHORT`.
+    pub index_type: u32,
+    /// Offset in bytes of the first index in the EBO.
+    pub index_offset: usize,
+    /// The number of indices, or of vertices if there is no EBO.
+    pub count: i32,
+    /// Points and lines may come without normals, in which case they are drawn unlit.
+    pub has_normals: bool,
+    /// Index into [`Renderer::materials`].
+    pub material: usize,
+    pub transform: glam::Mat4,
+}
+
+pub struct Renderer {
+    program: u32,
+    buffers: Vec<u32>,
+    pub materials: Vec<Material>,
+    pub primitives: Vec<Primitive>,
+    pub clear_color: [f32; 4],
+    /// The scale of the scene, used to place the lights.
+    pub light_radius: f32,
+}
+
+impl Renderer {
+    /// Compiles the shaders and sets up global GL state. GL must already be loaded for the
+    /// current context.
+    pub fn new() -> Self {
+        let program = unsafe {
+            let vshader = compile_shader(gl::VERTEX_SHADER, VERTEX_SOURCE, "Vertex");
+            let fshader = compile_shader(gl::FRAGMENT_SHADER, FRAG_SOURCE, "Fragment");
+
+            let program = gl::CreateProgram();
+            gl::AttachShader(program, vshader);
+            gl::AttachShader(program, fshader);
+            gl::LinkProgram(program);
+
+            let mut program_linked = 0i32;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut program_linked);
+            if program_linked as u8 != gl::TRUE {
+                let mut log_length = 0;
+                let mut message: [gl::types::GLchar; 1024] = [0; 1024];
+                gl::GetProgramInfoLog(program, 1024, &mut log_length, message.as_mut_ptr());
+                log::error!(
+                    "Program link error: {:?}",
+                    std::ffi::CStr::from_ptr(message.as_ptr())
+                );
+            }
+
+            gl::DeleteShader(vshader);
+            gl::DeleteShader(fshader);
+
+            // Primitives without COLOR_0 read this instead, which leaves base color unchanged.
+            gl::VertexAttrib4f(COLOR_LOCATION, 1.0, 1.0, 1.0, 1.0);
+
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::FRAMEBUFFER_SRGB);
+
+            program
+        };
+
+        Renderer {
+            program,
+            buffers: Vec::new(),
+            materials: Vec::new(),
+            primitives: Vec::new(),
+            clear_color: [0.5, 0.5, 0.5, 1.0],
+            light_radius: 3.0,
+        }
+    }
+
+    /// Routes GL debug messages to the log.
+    pub fn enable_debug_output(&self) {
+        unsafe {
+            gl::DebugMessageCallback(Some(gl_debug_callback), std::ptr::null());
+            gl::DebugMessageControl(
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                0,
+                std::ptr::null(),
+                gl::TRUE,
+            );
+        }
+    }
+
+    /// Uploads the scene's meshes and textures to the GPU.
+    pub fn upload(&mut self, scene: &Scene) {
+        // Only the buffer views that meshes read from are uploaded, each with the target it is
+        // used for, so that images and anything else stored in the buffers stay on the CPU.
+        let mut vertex_buffers = HashMap::new();
+        let mut index_buffers = HashMap::new();
+        for prim in scene.meshes.iter().flat_map(|mesh| mesh.primitives.iter()) {
+            for (_, accessor) in prim.attributes.iter() {
+                if let Some(view) = scene.accessors[*accessor].view {
+                    vertex_buffers
+                        .entry(view)
+                        .or_insert_with(|| unsafe { upload_view(scene, view, gl::ARRAY_BUFFER) });
+                }
+            }
+            if let Some(view) = prim
+                .indices
+                .and_then(|indices| scene.accessors[indices].view)
+            {
+                index_buffers.entry(view).or_insert_with(|| unsafe {
+                    upload_view(scene, view, gl::ELEMENT_ARRAY_BUFFER)
+                });
+            }
+        }
+        self.buffers
+            .extend(vertex_buffers.values().chain(index_buffers.values()));
+
+        // Textures are shared between materials, but whether they hold sRGB data depends on
+        // how they are used, so each is uploaded once per color space it is used in.
+        let mut textures = HashMap::new();
+        let mut upload_texture = |texture_ref: scene::TextureRef, srgb: bool| {
+            let id = *textures
+                .entry((texture_ref.texture, srgb))
+                .or_insert_with(|| unsafe {
+                    generate_texture(scene, &scene.textures[texture_ref.texture], srgb)
+                });
+            Texture {
+                id,
+                tex_coord: texture_ref.tex_coord,
+            }
+        };
+
+        // The default material goes last, for primitives that don't specify one.
+        let first_material = self.materials.len();
+        let default_material = scene::Material::default();
+        for material in scene.materials.iter().chain(Some(&default_material)) {
+            self.materials.push(Material {
+                base_color_factor: material.base_color_factor,
+                base_color_texture: material
+                    .base_color_texture
+                    .map(|info| upload_texture(info, true)),
+                metallic_factor: material.metallic_factor,
+                roughness_factor: material.roughness_factor,
+                metallic_roughness_texture: material
+                    .metallic_roughness_texture
+                    .map(|info| upload_texture(info, false)),
+                normal_texture: material
+                    .normal_texture
+                    .map(|info| upload_texture(info, false)),
+                normal_scale: material.normal_scale,
+                occlusion_texture: material
+                    .occlusion_texture
+                    .map(|info| upload_texture(info, false)),
+                occlusion_strength: material.occlusion_strength,
+                emissive_texture: material
+                    .emissive_texture
+                    .map(|info| upload_texture(info, true)),
+                emissive_factor: material.emissive_factor,
+                alpha_mode: material.alpha_mode,
+                alpha_cutoff: material.alpha_cutoff,
+                double_sided: material.double_sided,
+            });
+        }
+        let default_material = self.materials.len() - 1;
+
+        for node in scene.nodes.iter() {
+            let mesh = match node.mesh {
+                Some(mesh) => &scene.meshes[mesh],
+                None => continue,
+            };
+
+            for prim in mesh.primitives.iter() {
+                unsafe {
+                    let mut vao = 0;
+                    gl::GenVertexArrays(1, &mut vao);
+
+                    let vertex_attrib = |attrib_idx: u32, sem: scene::Semantic| {
+                        let accessor = if let Some(accessor) = prim.attribute(&sem) {
+                            &scene.accessors[accessor]
+                        } else {
+                            return;
+                        };
+                        let view = match accessor.view {
+                            Some(view) => view,
+                            None => return,
+                        };
+                        gl::BindVertexArray(vao);
+                        gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[&view]);
+                        let attribute_multipicity = accessor.dimensions.multiplicity();
+                        let stride = scene.views[view]
+                            .stride
+                            .unwrap_or_else(|| accessor.element_size());
+                        let offset = accessor.offset;
+
+                        log::debug!(
+                            "Got accessor {{ size = {}, dimesions = {:?}, multiplicity = {}, normalized = {}, type = {:?} }}",
+                            accessor.element_size(),
+                            accessor.dimensions,
+                            accessor.dimensions.multiplicity(),
+                            accessor.normalized,
+                            accessor.data_type
+                        );
+
+                        // Integer components are converted to floats by GL, either mapped to
+                        // [0, 1] / [-1, 1] when normalized or as-is otherwise (as quantized
+                        // positions from KHR_mesh_quantization are).
+                        gl::VertexAttribPointer(
+                            attrib_idx,
+                            attribute_multipicity as i32,
+                            accessor.data_type.as_gl_enum(),
+                            accessor.normalized as u8,
+                            stride as i32,
+                            offset as *const std::ffi::c_void,
+                        );
+                        gl::EnableVertexAttribArray(attrib_idx);
+                        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                        gl::BindVertexArray(0);
+                    };
+                    vertex_attrib(POSITION_LOCATION, scene::Semantic::Positions);
+                    vertex_attrib(NORMAL_LOCATION, scene::Semantic::Normals);
+                    vertex_attrib(TANGENT_LOCATION, scene::Semantic::Tangents);
+                    vertex_attrib(COLOR_LOCATION, scene::Semantic::Colors(0));
+                    for (set, location) in TEX_COORD_LOCATIONS.iter().enumerate() {
+                        vertex_attrib(*location, scene::Semantic::TexCoords(set as u32));
+                    }
+
+                    let (ebo, index_type, index_offset, count) =
+                        match prim.indices.map(|indices| &scene.accessors[indices]) {
+                            Some(accessor) if accessor.view.is_some() => {
+                                let ebo = index_buffers[&accessor.view.unwrap()];
+                                let index_type = match accessor.data_type {
+                                    scene::DataType::U8 => gl::UNSIGNED_BYTE,
+                                    scene::DataType::U16 => gl::UNSIGNED_SHORT,
+                                    scene::DataType::U32 => gl::UNSIGNED_INT,
+                                    other => unreachable!("the loader rejects {:?} indices", other),
+                                };
+                                (Some(ebo), index_type, accessor.offset, accessor.count)
+                            }
+                            // Without indices every vertex is drawn in order; the spec requires all
+                            // attributes to have the same count, so any one will do.
+                            _ => {
+                                let count = prim
+                                    .attribute(&scene::Semantic::Positions)
+                                    .map_or(0, |accessor| scene.accessors[accessor].count);
+                                (None, 0, 0, count)
+                            }
+                        };
+
+                    self.primitives.push(Primitive {
+                        mode: prim.mode.as_gl_enum(),
+                        vao,
+                        ebo,
+                        index_type,
+                        index_offset,
+                        count: count as i32,
+                        has_normals: prim.attribute(&scene::Semantic::Normals).is_some(),
+                        material: prim
+                            .material
+                            .map_or(default_material, |material| first_material + material),
+                        transform: node.world_transform,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Clears the current framebuffer and draws everything uploaded so far.
+    pub fn draw(&self, camera: &Camera, aspect_ratio: f32) {
+        let program = self.program;
+        unsafe {
+            let view_matrix = camera.get_view();
+            let [r, g, b, a] = self.clear_color;
+            gl::ClearColor(r, g, b, a);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+            let proj_matrix = camera.get_projection(aspect_ratio);
+
+            let uniform_location = |s: &str| {
+                let name = std::ffi::CString::new(s).unwrap();
+                gl::GetUniformLocation(program, name.as_ptr())
+            };
+
+            gl::ProgramUniformMatrix4fv(
+                program,
+                uniform_location("u_view"),
+                1,
+                gl::FALSE,
+                view_matrix.to_cols_array().as_ptr(),
+            );
+
+            gl::ProgramUniformMatrix4fv(
+                program,
+                uniform_location("u_proj"),
+                1,
+                gl::FALSE,
+                proj_matrix.to_cols_array().as_ptr(),
+            );
+
+            gl::ProgramUniform1f(
+                program,
+                uniform_location("u_camera_radius"),
+                self.light_radius,
+            );
+
+            for primitive in self.primitives.iter() {
+                let material = &self.materials[primitive.material];
+
+                gl::ProgramUniformMatrix4fv(
+                    program,
+                    uniform_location("u_model"),
+                    1,
+                    gl::FALSE,
+                    primitive.transform.to_cols_array().as_ptr(),
+                );
+
+                gl::ProgramUniform4fv(
+                    program,
+                    uniform_location("u_base_color_factor"),
+                    1,
+                    material.base_color_factor.to_array().as_ptr(),
+                );
+                gl::ProgramUniform1f(
+                    program,
+                    uniform_location("u_metallic_factor"),
+                    material.metallic_factor,
+                );
+                gl::ProgramUniform1f(
+                    program,
+                    uniform_location("u_roughness_factor"),
+                    material.roughness_factor,
+                );
+                gl::ProgramUniform1f(
+                    program,
+                    uniform_location("u_normal_scale"),
+                    material.normal_scale,
+                );
+                gl::ProgramUniform1f(
+                    program,
+                    uniform_location("u_occlusion_strength"),
+                    material.occlusion_strength,
+                );
+                gl::ProgramUniform3fv(
+                    program,
+                    uniform_location("u_emissive_factor"),
+                    1,
+                    material.emissive_factor.to_array().as_ptr(),
+                );
+                gl::ProgramUniform1ui(
+                    program,
+                    uniform_location("u_has_normals"),
+                    primitive.has_normals as u32,
+                );
+
+                let bind_texture = |unit: u32, name: &str, texture: Option<Texture>| {
+                    gl::ActiveTexture(gl::TEXTURE0 + unit);
+                    gl::BindTexture(gl::TEXTURE_2D, texture.map_or(0, |t| t.id));
+                    gl::ProgramUniform1i(
+                        program,
+                        uniform_location(&format!("u_{}_texture", name)),
+                        unit as i32,
+                    );
+                    gl::ProgramUniform1ui(
+                        program,
+                        uniform_location(&format!("u_{}_texture_exists", name)),
+                        texture.is_some() as u32,
+                    );
+                    gl::ProgramUniform1i(
+                        program,
+                        uniform_location(&format!("u_{}_uv_set", name)),
+                        texture.map_or(0, |t| t.tex_coord) as i32,
+                    );
+                };
+                bind_texture(0, "base_color", material.base_color_texture);
+                bind_texture(1, "normal", material.normal_texture);
+                bind_texture(2, "metallic_roughness", material.metallic_roughness_texture);
+                bind_texture(3, "occlusion", material.occlusion_texture);
+                bind_texture(4, "emissive", material.emissive_texture);
+
+                if material.double_sided {
+                    gl::Disable(gl::CULL_FACE);
+                } else {
+                    gl::Enable(gl::CULL_FACE);
+                }
+                // A mirroring transform turns the winding of front faces around.
+                if primitive.transform.determinant() < 0.0 {
+                    gl::FrontFace(gl::CW);
+                } else {
+                    gl::FrontFace(gl::CCW);
+                }
+
+                gl::UseProgram(program);
+
+                // Note: Need to bind the VAO before the EBO, since the EBO will just point to the
+                // previous VAO otherwise.
+                gl::BindVertexArray(primitive.vao);
+                match primitive.ebo {
+                    Some(ebo) => {
+                        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+                        gl::DrawElements(
+                            primitive.mode,
+                            primitive.count,
+                            primitive.index_type,
+                            primitive.index_offset as *const std::ffi::c_void,
+                        );
+                    }
+                    None => gl::DrawArrays(primitive.mode, 0, primitive.count),
+                }
+            }
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe fn compile_shader(kind: gl::types::GLenum, source: &str, label: &str) -> u32 {
+    let shader = gl::CreateShader(kind);
+    let source = std::ffi::CString::new(source).unwrap();
+    gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
+    gl::CompileShader(shader);
+
+    let mut compiled = 0i32;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compiled);
+
+    if compiled as u8 != gl::TRUE {
+        let mut log_length = 0;
+        let mut message: [gl::types::GLchar; 1024] = [0; 1024];
+        gl::GetShaderInfoLog(shader, 1024, &mut log_length, message.as_mut_ptr());
+        log::error!(
+            "{} shader error: {:?}",
+            label,
+            std::ffi::CStr::from_ptr(message.as_ptr())
+        );
+    }
+
+    shader
+}
+
+/// Copies the bytes of a buffer view into a new GL buffer bound to `target`.
+unsafe fn upload_view(scene: &Scene, view: usize, target: u32) -> u32 {
+    let view = &scene.views[view];
+    let bytes = &scene.buffers[view.buffer][view.offset..view.offset + view.length];
+    log::debug!("Buffering num_bytes={}", bytes.len());
+
+    let mut buffer = 0;
+    gl::GenBuffers(1, &mut buffer);
+    gl::BindBuffer(target, buffer);
+    gl::BufferData(
+        target,
+        bytes.len() as isize,
+        bytes.as_ptr() as *const std::ffi::c_void,
+        gl::STATIC_DRAW,
+    );
+    gl::BindBuffer(target, 0);
+    buffer
+}
+
+unsafe fn generate_texture(scene: &Scene, texture: &scene::Texture, srgb_remap: bool) -> u32 {
+    let mut tex_id = 0u32;
+    gl::GenTextures(1, &mut tex_id);
+    gl::BindTexture(gl::TEXTURE_2D, tex_id);
+
+    // Set up texture sampling parameters.
+    let sampler = &texture.sampler;
+    if let Some(mag_filter) = sampler.mag_filter {
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            mag_filter.as_gl_enum() as i32,
+        );
+    }
+    if let Some(min_filter) = sampler.min_filter {
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            min_filter.as_gl_enum() as i32,
+        );
+    }
+    gl::TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_S,
+        sampler.wrap_s.as_gl_enum() as i32,
+    );
+    gl::TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_T,
+        sampler.wrap_t.as_gl_enum() as i32,
+    );
+
+    let fmt_from_depth = |depth, is_u8| match depth {
+        1 => (if is_u8 { gl::R8 } else { gl::R32F }, gl::RED),
+        2 => (if is_u8 { gl::RG8 } else { gl::RG32F }, gl::RG),
+        3 => (
+            match (is_u8, srgb_remap) {
+                (true, true) => gl::SRGB8,
+                (true, false) => gl::RGB8,
+                (false, _) => gl::RGB32F,
+            },
+            gl::RGB,
+        ),
+        4 => (
+            match (is_u8, srgb_remap) {
+                (true, true) => gl::SRGB_ALPHA,
+                (true, false) => gl::RGBA8,
+                (false, _) => gl::RGBA32F,
+            },
+            gl::RGBA,
+        ),
+        _ => unreachable!("the loader rejects images with {} channels", depth),
+    };
+
+    let image = &scene.images[texture.image];
+    match image.pixels {
+        scene::Pixels::U8(ref data) => {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                fmt_from_depth(image.channels, true).0 as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                fmt_from_depth(image.channels, true).1,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+        }
+        scene::Pixels::F32(ref data) => {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                fmt_from_depth(image.channels, false).0 as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                fmt_from_depth(image.channels, false).1,
+                gl::FLOAT,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+        }
+    }
+    gl::GenerateMipmap(gl::TEXTURE_2D);
+    gl::BindTexture(gl::TEXTURE_2D, 0);
+
+    tex_id
+}
+
+extern "system" fn gl_debug_callback(
+    _source: u32,
+    _type: u32,
+    _id: u32,
+    _sev: u32,
+    _length: i32,
+    msg: *const gl::types::GLchar,
+    _data: *mut std::ffi::c_void,
+) {
+    log::warn!("OpenGL errored: {:?}", unsafe {
+        std::ffi::CStr::from_ptr(msg)
+    });
+}
+
+const VERTEX_SOURCE: &str = "
+#version 330 core
+layout (location = 0) in vec3 a_pos;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv0;
+layout (location = 3) in vec4 a_tangent;
+layout (location = 4) in vec2 a_uv1;
+layout (location = 5) in vec4 a_color;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_proj;
+uniform float u_camera_radius;
+
+out vec3 io_position;
+out vec3 io_light_pos[2];
+out vec3 io_normal;
+out vec2 io_uv[2];
+out vec4 io_color;
+out mat3 io_tbn;
+
+void main() {
+
+  vec3 bitangent = cross(a_normal, a_tangent.xyz) * a_tangent.w;
+  vec3 view_bitangent = vec3(u_view * u_model * vec4(bitangent, 0.0));
+  vec3 view_normal = vec3(u_view * u_model * vec4(a_normal, 0.0));
+  vec3 view_tangent = vec3(u_view * u_model * vec4(a_tangent.xyz, 0.0));
+  io_tbn = mat3(view_tangent, view_bitangent, view_normal);
+
+  io_light_pos[0] = vec3(u_view * vec4(0.0, u_camera_radius / 2.0, -u_camera_radius, 1.0));
+  io_light_pos[1] = vec3(u_view * vec4(0.0, u_camera_radius, u_camera_radius, 1.0));
+  io_position = vec3(u_view * u_model * vec4(a_pos, 1.0));
+  io_normal = view_normal;
+  io_uv[0] = a_uv0;
+  io_uv[1] = a_uv1;
+  io_color = a_color;
+
+  gl_Position = u_proj * u_view * u_model * vec4(a_pos, 1.0);
+}
+";
+
+const FRAG_SOURCE: &str = "
+#version 330 core
+
+out vec4 FragColor;
+
+const float PI = 3.14159265359;
+
+vec3 k_light_color = vec3(1.0, 1.0, 1.0);
+float k_light_intensity = 2.5;
+// Stands in for image based lighting, so that metals aren't black away from the lights.
+vec3 k_ambient_color = vec3(0.3, 0.3, 0.3);
+
+uniform vec4 u_base_color_factor;
+uniform sampler2D u_base_color_texture;
+uniform bool u_base_color_texture_exists;
+uniform int u_base_color_uv_set;
+
+uniform float u_metallic_factor;
+uniform float u_roughness_factor;
+uniform sampler2D u_metallic_roughness_texture;
+uniform bool u_metallic_roughness_texture_exists;
+uniform int u_metallic_roughness_uv_set;
+
+uniform float u_normal_scale;
+uniform sampler2D u_normal_texture;
+uniform bool u_normal_texture_exists;
+uniform int u_normal_uv_set;
+
+uniform float u_occlusion_strength;
+uniform sampler2D u_occlusion_texture;
+uniform bool u_occlusion_texture_exists;
+uniform int u_occlusion_uv_set;
+
+uniform vec3 u_emissive_factor;
+uniform sampler2D u_emissive_texture;
+uniform bool u_emissive_texture_exists;
+uniform int u_emissive_uv_set;
+
+uniform bool u_has_normals;
+
+in vec3 io_position;
+in vec3 io_light_pos[2];
+in vec3 io_normal;
+in vec2 io_uv[2];
+in vec4 io_color;
+in mat3 io_tbn;
+
+// Trowbridge-Reitz (GGX) microfacet distribution.
+float distribution_ggx(float n_dot_h, float alpha) {
+    float alpha2 = alpha * alpha;
+    float f = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * f * f);
+}
+
+// Height-correlated Smith-GGX masking-shadowing, folded together with the 1 / (4 n.l n.v)
+// denominator of the specular BRDF.
+float visibility_smith_ggx(float n_dot_l, float n_dot_v, float alpha) {
+    float alpha2 = alpha * alpha;
+    float ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha2) + alpha2);
+    float ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha2) + alpha2);
+    float ggx = ggx_v + ggx_l;
+    return ggx > 0.0 ? 0.5 / ggx : 0.0;
+}
+
+vec3 fresnel_schlick(vec3 f0, float v_dot_h) {
+    return f0 + (vec3(1.0) - f0) * pow(clamp(1.0 - v_dot_h, 0.0, 1.0), 5.0);
+}
+
+// Karis' analytic fit of the pre-integrated specular BRDF, used for the ambient term.
+vec3 env_brdf_approx(vec3 f0, float roughness, float n_dot_v) {
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+void main() {
+    vec4 base_color = u_base_color_factor * io_color;
+    if (u_base_color_texture_exists) {
+        base_color *= texture(u_base_color_texture, io_uv[u_base_color_uv_set]);
+    }
+
+    // Roughness is in the green channel and metalness in the blue channel.
+    float metallic = u_metallic_factor;
+    float roughness = u_roughness_factor;
+    if (u_metallic_roughness_texture_exists) {
+        vec4 metallic_roughness = texture(u_metallic_roughness_texture, io_uv[u_metallic_roughness_uv_set]);
+        roughness *= metallic_roughness.g;
+        metallic *= metallic_roughness.b;
+    }
+    metallic = clamp(metallic, 0.0, 1.0);
+    roughness = clamp(roughness, 0.03, 1.0);
+    float alpha = roughness * roughness;
+
+    vec3 emissive = u_emissive_factor;
+    if (u_emissive_texture_exists) {
+        emissive *= texture(u_emissive_texture, io_uv[u_emissive_uv_set]).rgb;
+    }
+    if (!u_has_normals) {
+        FragColor = vec4(base_color.rgb + emissive, base_color.a);
+        return;
+    }
+
+    vec3 normal = normalize(io_normal);
+    if (u_normal_texture_exists) {
+        vec3 tangent_normal = texture(u_normal_texture, io_uv[u_normal_uv_set]).xyz * 2.0 - 1.0;
+        tangent_normal.xy *= u_normal_scale;
+        normal = normalize(io_tbn * tangent_normal);
+    }
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
+
+    vec3 to_camera = normalize(-io_position);
+    float n_dot_v = clamp(dot(normal, to_camera), 0.001, 1.0);
+
+    vec3 c_diff = mix(base_color.rgb, vec3(0.0), metallic);
+    vec3 f0 = mix(vec3(0.04), base_color.rgb, metallic);
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < 2; i++) {
+        vec3 to_light = normalize(io_light_pos[i] - io_position);
+        vec3 halfway = normalize(to_camera + to_light);
+        float n_dot_l = clamp(dot(normal, to_light), 0.0, 1.0);
+        float n_dot_h = clamp(dot(normal, halfway), 0.0, 1.0);
+        float v_dot_h = clamp(dot(to_camera, halfway), 0.0, 1.0);
+
+        vec3 fresnel = fresnel_schlick(f0, v_dot_h);
+        vec3 diffuse = (vec3(1.0) - fresnel) * c_diff / PI;
+        vec3 specular = fresnel * distribution_ggx(n_dot_h, alpha)
+            * visibility_smith_ggx(n_dot_l, n_dot_v, alpha);
+
+        color += k_light_intensity * k_light_color * n_dot_l * (diffuse + specular);
+    }
+
+    // Occlusion only darkens indirect light, which here is the ambient term.
+    float occlusion = 1.0;
+    if (u_occlusion_texture_exists) {
+        occlusion = 1.0 + u_occlusion_strength * (texture(u_occlusion_texture, io_uv[u_occlusion_uv_set]).r - 1.0);
+    }
+    color += occlusion * k_ambient_color * (c_diff + env_brdf_approx(f0, roughness, n_dot_v));
+
+    color += emissive;
+
+    FragColor = vec4(color, base_color.a);
+}
+";
@@ -0,0 +1,496 @@
+//! A renderer-independent description of a glTF scene.
+//!
+//! Everything here is plain data: indices into the vectors of [`Scene`] take the place of the
+//! references in a `gltf::Document`, and nothing touches GL. [`crate::loader`] builds a
+//! [`Scene`] and [`crate::renderer::Renderer`] uploads one.
+
+pub use gltf::accessor::{DataType, Dimensions};
+pub use gltf::material::AlphaMode;
+pub use gltf::mesh::{Mode, Semantic};
+pub use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+use std::fmt;
+
+/// The number of `TEXCOORD_n` attributes a material may sample from.
+pub const MAX_TEX_COORD_SETS: u32 = 2;
+
+#[derive(Default)]
+pub struct Scene {
+    /// The raw bytes of each buffer.
+    pub buffers: Vec<Vec<u8>>,
+    pub views: Vec<BufferView>,
+    pub accessors: Vec<Accessor>,
+    pub images: Vec<Image>,
+    pub textures: Vec<Texture>,
+    pub materials: Vec<Material>,
+    pub meshes: Vec<Mesh>,
+    pub cameras: Vec<Camera>,
+    pub lights: Vec<Light>,
+    /// Every node reachable from the scene's roots, parents before children.
+    pub nodes: Vec<Node>,
+    /// Features of the file that were not fully supported, in the order they were found.
+    pub warnings: Vec<Warning>,
+}
+
+pub struct BufferView {
+    pub buffer: usize,
+    pub offset: usize,
+    pub length: usize,
+    /// The distance in bytes between consecutive elements, if they are not tightly packed.
+    pub stride: Option<usize>,
+}
+
+pub struct Accessor {
+    /// The buffer view holding the data. Accessors without one are all zeros.
+    pub view: Option<usize>,
+    /// Offset in bytes from the start of the buffer view.
+    pub offset: usize,
+    pub count: usize,
+    pub data_type: DataType,
+    pub dimensions: Dimensions,
+    pub normalized: bool,
+    pub min: Option<Vec<f32>>,
+    pub max: Option<Vec<f32>>,
+}
+
+impl Accessor {
+    /// The size in bytes of a single element.
+    pub fn element_size(&self) -> usize {
+        self.data_type.size() * self.dimensions.multiplicity()
+    }
+}
+
+impl Scene {
+    pub fn warn(&mut self, location: Location, message: String) {
+        self.warnings.push(Warning { location, message });
+    }
+
+    /// Reads every component of an accessor, `dimensions.multiplicity()` per element, converting
+    /// each with `read`.
+    fn read_components<T>(&self, accessor: usize, read: impl Fn(&[u8]) -> T) -> Vec<T> {
+        let accessor = &self.accessors[accessor];
+        let size = accessor.data_type.size();
+        let components = accessor.dimensions.multiplicity();
+        match accessor.view {
+            None => {
+                let zero = [0u8; 4];
+                (0..accessor.count * components)
+                    .map(|_| read(&zero[..size]))
+                    .collect()
+            }
+            Some(view) => {
+                let view = &self.views[view];
+                let stride = view.stride.unwrap_or_else(|| accessor.element_size());
+                let bytes = &self.buffers[view.buffer]
+                    [view.offset + accessor.offset..view.offset + view.length];
+                (0..accessor.count)
+                    .flat_map(|i| (0..components).map(move |c| i * stride + c * size))
+                    .map(|start| read(&bytes[start..start + size]))
+                    .collect()
+            }
+        }
+    }
+
+    /// Reads an accessor as floats, applying the normalization rules for integer components.
+    pub fn read_floats(&self, accessor: usize) -> Vec<f32> {
+        let data_type = self.accessors[accessor].data_type;
+        let normalized = self.accessors[accessor].normalized;
+        self.read_components(accessor, |b| match data_type {
+            DataType::I8 if normalized => (b[0] as i8 as f32 / 127.0).max(-1.0),
+            DataType::I8 => b[0] as i8 as f32,
+            DataType::U8 if normalized => b[0] as f32 / 255.0,
+            DataType::U8 => b[0] as f32,
+            DataType::I16 if normalized => {
+                (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0)
+            }
+            DataType::I16 => i16::from_le_bytes([b[0], b[1]]) as f32,
+            DataType::U16 if normalized => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+            DataType::U16 => u16::from_le_bytes([b[0], b[1]]) as f32,
+            DataType::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+            DataType::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        })
+    }
+
+    /// Reads an accessor of unsigned integers, such as a primitive's indices.
+    pub fn read_indices(&self, accessor: usize) -> Vec<u32> {
+        self.read_components(accessor, |b| match b.len() {
+            1 => b[0] as u32,
+            2 => u16::from_le_bytes([b[0], b[1]]) as u32,
+            _ => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        })
+    }
+
+    /// Appends a new buffer holding `bytes` and a view covering all of it, returning the index of
+    /// the view.
+    pub fn push_view(&mut self, bytes: Vec<u8>) -> usize {
+        self.views.push(BufferView {
+            buffer: self.buffers.len(),
+            offset: 0,
+            length: bytes.len(),
+            stride: None,
+        });
+        self.buffers.push(bytes);
+        self.views.len() - 1
+    }
+
+    /// Appends a tightly packed accessor over a new buffer holding `bytes`, returning its index.
+    pub fn push_accessor(
+        &mut self,
+        bytes: Vec<u8>,
+        data_type: DataType,
+        dimensions: Dimensions,
+    ) -> usize {
+        let count = bytes.len() / (data_type.size() * dimensions.multiplicity());
+        let view = self.push_view(bytes);
+        self.accessors.push(Accessor {
+            view: Some(view),
+            offset: 0,
+            count,
+            data_type,
+            dimensions,
+            normalized: false,
+            min: None,
+            max: None,
+        });
+        self.accessors.len() - 1
+    }
+
+    /// The bounds of a mesh in its own space, from the `POSITION` accessors of its primitives.
+    pub fn mesh_bounds(&self, mesh: usize) -> Option<Aabb> {
+        self.meshes[mesh]
+            .primitives
+            .iter()
+            .filter_map(|primitive| primitive.attribute(&Semantic::Positions))
+            .filter_map(|positions| {
+                let accessor = &self.accessors[positions];
+                match (&accessor.min, &accessor.max) {
+                    // Bounds of integer accessors are unnormalized, so only trust float ones.
+                    (Some(min), Some(max))
+                        if accessor.data_type == DataType::F32
+                            && min.len() == 3
+                            && max.len() == 3 =>
+                    {
+                        Some(Aabb {
+                            min: glam::Vec3::from_slice(min),
+                            max: glam::Vec3::from_slice(max),
+                        })
+                    }
+                    _ => Aabb::from_points(
+                        self.read_floats(positions)
+                            .chunks_exact(3)
+                            .map(glam::Vec3::from_slice),
+                    ),
+                }
+            })
+            .reduce(Aabb::union)
+    }
+
+    /// The world-space bounds of a node's mesh, if it has one.
+    pub fn node_bounds(&self, node: &Node) -> Option<Aabb> {
+        self.mesh_bounds(node.mesh?)
+            .map(|bounds| bounds.transformed(node.world_transform))
+    }
+
+    /// Appends a float accessor holding `data`, returning its index.
+    pub fn push_float_accessor(&mut self, data: &[f32], dimensions: Dimensions) -> usize {
+        let bytes = data.iter().flat_map(|f| f.to_le_bytes().to_vec()).collect();
+        self.push_accessor(bytes, DataType::F32, dimensions)
+    }
+}
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Aabb {
+    pub fn from_points(points: impl Iterator<Item = glam::Vec3>) -> Option<Aabb> {
+        points.map(|p| Aabb { min: p, max: p }).reduce(Aabb::union)
+    }
+
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// The box around this one's corners after transforming them.
+    pub fn transformed(&self, transform: glam::Mat4) -> Aabb {
+        let corners = (0..8).map(|i| {
+            let pick = |bit, min: f32, max: f32| if i & bit == 0 { min } else { max };
+            transform.transform_point3(glam::Vec3::new(
+                pick(1, self.min.x, self.max.x),
+                pick(2, self.min.y, self.max.y),
+                pick(4, self.min.z, self.max.z),
+            ))
+        });
+        Aabb::from_points(corners).unwrap()
+    }
+
+    pub fn center(&self) -> glam::Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// The radius of the sphere through the corners.
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).length() / 2.0
+    }
+
+    /// How far along the ray from `origin` in `direction` it first enters the box, if it does.
+    pub fn intersect_ray(&self, origin: glam::Vec3, direction: glam::Vec3) -> Option<f32> {
+        let t0 = (self.min - origin) / direction;
+        let t1 = (self.max - origin) / direction;
+        let near = t0.min(t1).max_element().max(0.0);
+        let far = t0.max(t1).min_element();
+        if near <= far {
+            Some(near)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Image {
+    /// The URI or buffer view the image came from, for use in messages.
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub channels: usize,
+    pub pixels: Pixels,
+}
+
+pub enum Pixels {
+    U8(Vec<u8>),
+    F32(Vec<f32>),
+}
+
+pub struct Texture {
+    pub image: usize,
+    pub sampler: Sampler,
+}
+
+pub struct Sampler {
+    pub mag_filter: Option<MagFilter>,
+    pub min_filter: Option<MinFilter>,
+    pub wrap_s: WrappingMode,
+    pub wrap_t: WrappingMode,
+}
+
+/// A material's use of a texture.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureRef {
+    pub texture: usize,
+    /// Which `TEXCOORD_n` attribute to sample it with.
+    pub tex_coord: u32,
+}
+
+pub struct Material {
+    pub name: Option<String>,
+    pub base_color_factor: glam::Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<TextureRef>,
+    pub emissive_factor: glam::Vec3,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    /// The material the spec says to use for primitives without one.
+    fn default() -> Self {
+        Material {
+            name: None,
+            base_color_factor: glam::Vec4::ONE,
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+            emissive_factor: glam::Vec3::ZERO,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+pub struct Mesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+pub struct Primitive {
+    pub mode: Mode,
+    /// Accessor indices for each vertex attribute.
+    pub attributes: Vec<(Semantic, usize)>,
+    pub indices: Option<usize>,
+    /// `None` means the default material.
+    pub material: Option<usize>,
+}
+
+impl Primitive {
+    pub fn attribute(&self, semantic: &Semantic) -> Option<usize> {
+        self.attributes
+            .iter()
+            .find(|(s, _)| s == semantic)
+            .map(|(_, accessor)| *accessor)
+    }
+}
+
+pub struct Camera {
+    pub name: Option<String>,
+    pub projection: Projection,
+}
+
+pub enum Projection {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// `None` means an infinite projection.
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+/// A light from the `KHR_lights_punctual` extension.
+pub struct Light {
+    pub name: Option<String>,
+    pub kind: LightKind,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub range: Option<f32>,
+}
+
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+pub struct Node {
+    /// The index of the node in the glTF document.
+    pub index: usize,
+    pub name: Option<String>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+    /// The transform relative to the parent node.
+    pub local_transform: glam::Mat4,
+    /// The transform relative to the scene root.
+    pub world_transform: glam::Mat4,
+}
+
+/// Something in a file that the loader could not fully support, and what it did instead.
+pub struct Warning {
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// The part of a glTF document a [`Warning`] is about. Indices are those of the document.
+#[derive(Clone, Copy, Debug)]
+pub enum Location {
+    Document,
+    Accessor(usize),
+    Image(usize),
+    Material(usize),
+    Mesh(usize),
+    Primitive { mesh: usize, primitive: usize },
+    Node(usize),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Document => write!(f, "document"),
+            Location::Accessor(i) => write!(f, "accessor#{}", i),
+            Location::Image(i) => write!(f, "image#{}", i),
+            Location::Material(i) => write!(f, "material#{}", i),
+            Location::Mesh(i) => write!(f, "mesh#{}", i),
+            Location::Primitive { mesh, primitive } => {
+                write!(f, "mesh#{} primitive#{}", mesh, primitive)
+            }
+            Location::Node(i) => write!(f, "node#{}", i),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: glam::Vec3::ZERO,
+            max: glam::Vec3::ONE,
+        }
+    }
+
+    #[test]
+    fn transformed_bounds_contain_every_corner() {
+        let transform = glam::Mat4::from_translation(glam::Vec3::new(10.0, 0.0, 0.0))
+            * glam::Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2)
+            * glam::Mat4::from_scale(glam::Vec3::new(2.0, 1.0, 1.0));
+        let bounds = unit_box().transformed(transform);
+        // The box is stretched along x, then turned so that x points along -z.
+        assert!(bounds
+            .min
+            .abs_diff_eq(glam::Vec3::new(10.0, 0.0, -2.0), 1e-5));
+        assert!(bounds
+            .max
+            .abs_diff_eq(glam::Vec3::new(11.0, 1.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn ray_enters_box_at_its_near_face() {
+        let hit = unit_box().intersect_ray(glam::Vec3::new(0.5, 0.5, 5.0), -glam::Vec3::Z);
+        assert_eq!(hit, Some(4.0));
+    }
+
+    #[test]
+    fn ray_from_inside_box_hits_immediately() {
+        let hit = unit_box().intersect_ray(glam::Vec3::splat(0.5), glam::Vec3::X);
+        assert_eq!(hit, Some(0.0));
+    }
+
+    #[test]
+    fn ray_misses_box() {
+        let bounds = unit_box();
+        // Pointing away, and passing beside it.
+        assert_eq!(
+            bounds.intersect_ray(glam::Vec3::new(0.5, 0.5, 5.0), glam::Vec3::Z),
+            None
+        );
+        assert_eq!(
+            bounds.intersect_ray(glam::Vec3::new(2.0, 0.5, 5.0), -glam::Vec3::Z),
+            None
+        );
+    }
+}